@@ -0,0 +1,50 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use file::{chunk::Options, file::ChunkedFile};
+use nectar_primitives_traits::BzzReference;
+
+#[derive(Parser)]
+#[clap(about = "Compute the swarm reference of a file")]
+struct Arguments {
+    #[clap(help = "File to hash, read from stdin if omitted")]
+    path: Option<PathBuf>,
+    #[clap(long, help = "Encrypt the file, producing a 64 byte reference")]
+    encrypt: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Arguments::parse();
+
+    let reader: Box<dyn Read> = match &args.path {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+
+    println!("{}", hash(reader, args.encrypt)?);
+
+    Ok(())
+}
+
+/// Compute the reference of everything read from `reader`.
+///
+/// Plain references are computed without holding the file in memory. Encryption draws a random
+/// key for every chunk, so the whole file is chunked in memory and the reference differs on
+/// every run.
+fn hash(mut reader: impl Read, encrypt: bool) -> anyhow::Result<BzzReference> {
+    if encrypt {
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload)?;
+
+        let (_, reference) = ChunkedFile::new(payload, Options::default()).encrypt();
+        return Ok(reference);
+    }
+
+    let address = ChunkedFile::hash_reader(reader, Options::default())?;
+
+    Ok(BzzReference::Plain(address.into()))
+}
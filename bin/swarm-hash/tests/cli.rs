@@ -0,0 +1,61 @@
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../crates/primitives/test-files/bos.pdf"
+);
+const FIXTURE_REFERENCE: &str = "b8d17f296190ccc09a2c36b7a59d0f23c4479a3958c3bb02dc669466ec919c5d";
+
+fn swarm_hash(args: &[&str], stdin: Option<&[u8]>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_swarm-hash"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // dropping stdin closes it, so the binary sees the end of input
+    let mut child_stdin = child.stdin.take().unwrap();
+    if let Some(stdin) = stdin {
+        child_stdin.write_all(stdin).unwrap();
+    }
+    drop(child_stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    output
+}
+
+#[test]
+fn hashes_file() {
+    let output = swarm_hash(&[FIXTURE], None);
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().trim(),
+        FIXTURE_REFERENCE
+    );
+}
+
+#[test]
+fn hashes_stdin() {
+    let fixture = std::fs::read(FIXTURE).unwrap();
+    let output = swarm_hash(&[], Some(&fixture));
+
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().trim(),
+        FIXTURE_REFERENCE
+    );
+}
+
+#[test]
+fn encrypted_reference() {
+    let output = swarm_hash(&["--encrypt"], Some(b"hello swarm"));
+    let reference = String::from_utf8(output.stdout).unwrap();
+
+    // address followed by the decryption key, both hex encoded
+    assert_eq!(reference.trim().len(), 128);
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use file::{chunk::Options, file::ChunkedFile};
+use manifest::{
+    import::{content_type, CONTENT_TYPE_KEY, FILENAME_KEY},
+    persist::FsLoaderSaver,
+    Entry, Manifest,
+};
+use nectar_primitives_traits::BzzReference;
+
+#[derive(Parser)]
+#[clap(about = "Split a file or directory into chunks written to disk")]
+struct Arguments {
+    #[clap(help = "File or directory to split")]
+    path: PathBuf,
+    #[clap(long, help = "Directory to write the chunks to", default_value = "out")]
+    out: PathBuf,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> anyhow::Result<()> {
+    let args = Arguments::parse();
+
+    let reference = if args.path.is_dir() {
+        split_dir(&args.path, &args.out).await?
+    } else {
+        split_file(&args.path, &args.out)?
+    };
+
+    println!("{}", reference);
+
+    Ok(())
+}
+
+/// Write every chunk of the file at `path` to `out`, returning the file reference.
+///
+/// Each chunk is written to a file named by its hex encoded address, holding the span followed
+/// by the payload as they are sent to a bee node.
+fn split_file(path: &Path, out: &Path) -> anyhow::Result<BzzReference> {
+    let chunked_file = ChunkedFile::new(fs::read(path)?, Options::default());
+
+    fs::create_dir_all(out)?;
+    for chunk in chunked_file.all_chunks() {
+        let reference = BzzReference::Plain(chunk.address().into());
+        let data = [
+            &chunk.span().to_bytes()[..],
+            &chunk.data()[..chunk.payload_length],
+        ]
+        .concat();
+
+        fs::write(out.join(reference.to_string()), data)?;
+    }
+
+    Ok(chunked_file.reference())
+}
+
+/// Split every file below `dir` into `out`, and store a manifest mapping the relative file
+/// paths to their references, returning the manifest reference.
+///
+/// Manifest nodes are written to `out` alongside the chunks, named by the keccak256 hash of
+/// their data.
+async fn split_dir(dir: &Path, out: &Path) -> anyhow::Result<BzzReference> {
+    let mut paths = Vec::new();
+    walk(dir, &mut paths)?;
+
+    let mut m = Manifest::new(Box::new(FsLoaderSaver::new(out)), false);
+    for path in paths {
+        let reference = split_file(&path, out)?;
+
+        let relative = path
+            .strip_prefix(dir)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert(
+            CONTENT_TYPE_KEY.to_string(),
+            content_type(&relative).to_string(),
+        );
+        if let Some(name) = path.file_name() {
+            metadata.insert(FILENAME_KEY.to_string(), name.to_string_lossy().to_string());
+        }
+
+        m.add(
+            &relative,
+            Entry {
+                reference: reference.to_vec(),
+                metadata,
+            },
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("could not add {}: {}", relative, e))?;
+    }
+
+    let reference = m
+        .store()
+        .await
+        .map_err(|e| anyhow::anyhow!("could not store manifest: {}", e))?;
+
+    Ok(BzzReference::try_from(reference.as_slice())?)
+}
+
+/// Collect the paths of all files below `dir`, in a stable order.
+fn walk(dir: &Path, paths: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use std::{fs, path::Path, process::Command};
+
+use file::{
+    chunk::{Chunk, Options},
+    file::ChunkedFile,
+};
+use nectar_primitives_traits::BzzReference;
+
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../crates/primitives/test-files/carrier-chunk-blob"
+);
+
+fn swarm_split(path: &Path, out: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_swarm-split"))
+        .arg(path)
+        .arg("--out")
+        .arg(out)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+// read_chunks loads every chunk written to out, which is expected to hold no manifest nodes.
+fn read_chunks(out: &Path) -> Vec<Chunk> {
+    fs::read_dir(out)
+        .unwrap()
+        .map(|e| fs::read(e.unwrap().path()).unwrap())
+        .map(|data| {
+            let span = u64::from_le_bytes(data[..8].try_into().unwrap());
+            Chunk::new(&data[8..], Some(span), Options::default(), None)
+        })
+        .collect()
+}
+
+#[test]
+fn splits_and_rejoins_file() {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("swarm-split-file");
+    let _ = fs::remove_dir_all(&out);
+
+    let reference = swarm_split(Path::new(FIXTURE), &out);
+    assert!(out.join(&reference).is_file());
+
+    let root = reference.parse::<BzzReference>().unwrap().address().0;
+    let joined = ChunkedFile::from_root(root, &read_chunks(&out), Options::default()).unwrap();
+    assert_eq!(joined.payload(), fs::read(FIXTURE).unwrap());
+}
+
+#[test]
+fn splits_directory_with_manifest() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("swarm-split-dir");
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("swarm-split-dir-out");
+    let _ = fs::remove_dir_all(&dir);
+    let _ = fs::remove_dir_all(&out);
+
+    fs::create_dir_all(dir.join("img")).unwrap();
+    fs::write(dir.join("index.html"), b"<html></html>").unwrap();
+    fs::write(dir.join("img").join("1.png"), [1u8; 5000]).unwrap();
+
+    let reference = swarm_split(&dir, &out);
+    assert!(out.join(&reference).is_file());
+
+    let index = ChunkedFile::new(b"<html></html>".to_vec(), Options::default());
+    assert!(out.join(index.reference().to_string()).is_file());
+}
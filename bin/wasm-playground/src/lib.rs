@@ -7,9 +7,11 @@ use rand::RngCore;
 use tracing::{debug, error, info, trace, warn};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
 use web_sys::HtmlTextAreaElement;
 use web_sys::window;
 use web_sys::{Event, HtmlButtonElement, HtmlDivElement, HtmlInputElement};
+use web_sys::{Request, RequestInit, RequestMode, Response};
 
 #[wasm_bindgen(start)]
 fn main() -> Result<(), JsValue> {
@@ -94,6 +96,34 @@ fn main() -> Result<(), JsValue> {
         .append_child(&batch_id_generate)
         .unwrap();
 
+    // Create a new div element to contain the bee endpoint
+    let endpoint_container = document
+        .create_element("div")
+        .unwrap()
+        .dyn_into::<HtmlDivElement>()
+        .unwrap();
+    endpoint_container.set_id("endpoint_container");
+    document
+        .body()
+        .unwrap()
+        .append_child(&endpoint_container)
+        .unwrap();
+
+    // Create a label for the bee endpoint
+    let endpoint_label = document.create_element("label").unwrap();
+    endpoint_label.set_text_content(Some("Bee endpoint:"));
+    endpoint_container.append_child(&endpoint_label).unwrap();
+
+    // Create an input field for the bee endpoint
+    let endpoint_input = document
+        .create_element("input")
+        .unwrap()
+        .dyn_into::<HtmlInputElement>()
+        .unwrap();
+    endpoint_input.set_id("endpoint");
+    endpoint_input.set_value("http://localhost:1633");
+    endpoint_container.append_child(&endpoint_input).unwrap();
+
     // Create a new div element to put in two fields:
     // - the index of the bucket
     // - where in the bucket the chunk is
@@ -196,6 +226,15 @@ fn main() -> Result<(), JsValue> {
     button.set_inner_text("Calculate");
     button_container.append_child(&button).unwrap();
 
+    // Create an "Upload" button
+    let upload_button = document
+        .create_element("button")
+        .unwrap()
+        .dyn_into::<HtmlButtonElement>()
+        .unwrap();
+    upload_button.set_inner_text("Upload");
+    button_container.append_child(&upload_button).unwrap();
+
     // Create a div element to contain the output
     let output_container = document
         .create_element("div")
@@ -225,6 +264,33 @@ fn main() -> Result<(), JsValue> {
     output.set_id("output_text");
     output_container.append_child(&output).unwrap();
 
+    // Create a div element to contain the chunk tree
+    let tree_container = document
+        .create_element("div")
+        .unwrap()
+        .dyn_into::<HtmlDivElement>()
+        .unwrap();
+    tree_container.set_id("tree_container");
+    document
+        .body()
+        .unwrap()
+        .append_child(&tree_container)
+        .unwrap();
+
+    // Create a label for the chunk tree
+    let tree_label = document.create_element("label").unwrap();
+    tree_label.set_text_content(Some("Chunk tree:"));
+    tree_container.append_child(&tree_label).unwrap();
+
+    // Create a div element that the chunk tree is rendered into
+    let tree = document
+        .create_element("div")
+        .unwrap()
+        .dyn_into::<HtmlDivElement>()
+        .unwrap();
+    tree.set_id("chunk_tree");
+    tree_container.append_child(&tree).unwrap();
+
     // Create a new div element to contain the serialised pat
     let pat_container = document
         .create_element("div")
@@ -283,6 +349,51 @@ fn main() -> Result<(), JsValue> {
         .set_onclick(Some(batch_id_closure.as_ref().unchecked_ref()));
     batch_id_closure.forget();
 
+    // the closure that runs when the "Upload" button is clicked
+    let data_clone = data.clone();
+    let output_clone = output.clone();
+    let batch_id_input_clone = batch_id_input.clone();
+    let upload_closure = Closure::wrap(Box::new(move |_event: Event| {
+        let output = output_clone.clone();
+        let endpoint = endpoint_input.value();
+        let batch_id = batch_id_input_clone.value();
+
+        let f = bmt::file::ChunkedFile::new(
+            data_clone.value().as_bytes().to_vec(),
+            bmt::chunk::Options {
+                max_payload_size: 4096,
+            },
+        );
+        let address = hex::encode(f.address());
+        let chunks = f.all_chunks();
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let total = chunks.len();
+            for (i, chunk) in chunks.into_iter().enumerate() {
+                // a chunk is uploaded as its span followed by its payload, which for a leaf is
+                // only as long as the span
+                let span = chunk.span().value();
+                let data = chunk.data();
+                let len = data.len().min(span as usize);
+                let body = [&chunk.span().to_bytes()[..], &data[..len]].concat();
+
+                if let Err(e) = upload_chunk(&endpoint, &batch_id, &body).await {
+                    error!("could not upload chunk {} of {}: {:?}", i + 1, total, e);
+                    output.set_value(format!("Upload failed: {:?}", e).as_str());
+                    return;
+                }
+            }
+
+            info!("uploaded {} chunks", total);
+            output.set_value(
+                format!("Uploaded {} chunks, swarm hash: {:?}", total, address).as_str(),
+            );
+        });
+    }) as Box<dyn FnMut(Event)>);
+
+    upload_button.set_onclick(Some(upload_closure.as_ref().unchecked_ref()));
+    upload_closure.forget();
+
     // the closure that runs when the "Calculate" button is clicked
     let calculate_closure = Closure::wrap(Box::new(move |_event: Event| {
         let output_clone = output.clone();
@@ -298,6 +409,11 @@ fn main() -> Result<(), JsValue> {
         // Display the swarm hash
         output_clone.set_value(format!("Swarm hash: {:?}", hex::encode(f.address())).as_str());
 
+        // Display the chunk tree
+        if let Err(e) = render_chunk_tree(&tree, &f) {
+            error!("could not render the chunk tree: {:?}", e);
+        }
+
         // Get the batch owner's wallet (and therefore also stamp signer for Postman Pat)
         let wallet = pk_input.value().parse::<LocalWallet>();
         let wallet = match wallet {
@@ -342,7 +458,7 @@ fn main() -> Result<(), JsValue> {
         };
 
         // Create the batch
-        let batch = batch::Batch::new(
+        let batch = match batch::Batch::new(
             batch_id,
             0,
             None,
@@ -350,7 +466,13 @@ fn main() -> Result<(), JsValue> {
             batch_depth,
             bucket_depth,
             false
-        );
+        ) {
+            Ok(batch) => batch,
+            Err(e) => {
+                error!("could not create the batch: {:?}", e);
+                return;
+            }
+        };
 
         // Now that we have the batch, we can put Postman Pat to work! 📬
         let mut pat = postage::pat::Pat::new(&batch, 0, false, wallet);
@@ -376,3 +498,80 @@ fn main() -> Result<(), JsValue> {
 
     Ok(())
 }
+
+// render_chunk_tree replaces the contents of `tree` with a nested list of the levels of the
+// file's chunk tree, from the root down to the leaves. Each chunk is shown with its address
+// and span.
+fn render_chunk_tree(tree: &HtmlDivElement, f: &bmt::file::ChunkedFile) -> Result<(), JsValue> {
+    let document = window().unwrap().document().unwrap();
+    tree.set_inner_html("");
+
+    let levels = f.bmt();
+    let height = levels.len();
+
+    let list = document.create_element("ul")?;
+    for (i, level) in levels.iter().enumerate().rev() {
+        let item = document.create_element("li")?;
+        // a file that fits in a single chunk has one leaf, which is also the root
+        let name = match i {
+            _ if height == 1 => "root",
+            0 => "leaves",
+            _ if i == height - 1 => "root",
+            _ => "intermediate",
+        };
+        item.set_text_content(Some(
+            format!("Level {} ({}, {} chunks)", i, name, level.len()).as_str(),
+        ));
+
+        let chunks = document.create_element("ul")?;
+        for chunk in level {
+            let entry = document.create_element("li")?;
+            entry.set_text_content(Some(
+                format!(
+                    "{} (span {})",
+                    hex::encode(chunk.address()),
+                    chunk.span().value()
+                )
+                .as_str(),
+            ));
+            chunks.append_child(&entry)?;
+        }
+
+        item.append_child(&chunks)?;
+        list.append_child(&item)?;
+    }
+
+    tree.append_child(&list)?;
+
+    Ok(())
+}
+
+// upload_chunk posts a single chunk to the /chunks endpoint of a bee node, stamped with the
+// given batch. The node must allow cross origin requests from the playground.
+async fn upload_chunk(endpoint: &str, batch_id: &str, body: &[u8]) -> Result<(), JsValue> {
+    let body = js_sys::Uint8Array::from(body);
+
+    let mut opts = RequestInit::new();
+    opts.method("POST");
+    opts.mode(RequestMode::Cors);
+    opts.body(Some(&body));
+
+    let url = format!("{}/chunks", endpoint.trim_end_matches('/'));
+    let request = Request::new_with_str_and_init(&url, &opts)?;
+    request
+        .headers()
+        .set("Content-Type", "application/octet-stream")?;
+    request.headers().set("Swarm-Postage-Batch-Id", batch_id)?;
+
+    let response = JsFuture::from(window().unwrap().fetch_with_request(&request))
+        .await?
+        .dyn_into::<Response>()?;
+
+    if !response.ok() {
+        return Err(JsValue::from_str(
+            format!("bee responded with status {}", response.status()).as_str(),
+        ));
+    }
+
+    Ok(())
+}
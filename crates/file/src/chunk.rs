@@ -0,0 +1,170 @@
+use std::sync::OnceLock;
+
+use alloy::primitives::keccak256;
+
+use crate::{span::Span, SEGMENT_SIZE};
+
+/// The default maximum payload size of a chunk.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub max_payload_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+}
+
+/// A content addressed chunk, whose address is the hash of its span and the binary merkle tree
+/// root of its payload.
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    // zero padded to the maximum payload size
+    payload: Vec<u8>,
+    pub payload_length: usize,
+    span: Span,
+    options: Options,
+    stamp: Option<Vec<u8>>,
+    address: OnceLock<[u8; 32]>,
+}
+
+impl Chunk {
+    /// Create a chunk from `payload`, with a span of the payload length unless
+    /// `starting_span_value` is given, as for intermediate chunks.
+    ///
+    /// # Panics
+    ///
+    /// If the payload exceeds the maximum payload size of `options`.
+    pub fn new(
+        payload: &[u8],
+        starting_span_value: Option<u64>,
+        options: Options,
+        stamp: Option<Vec<u8>>,
+    ) -> Chunk {
+        let payload_length = payload.len();
+        assert!(
+            payload_length <= options.max_payload_size,
+            "payload of {} bytes exceeds the maximum of {}",
+            payload_length,
+            options.max_payload_size
+        );
+
+        let span = Span::new(starting_span_value.unwrap_or(payload_length as u64));
+        let mut padded = payload.to_vec();
+        padded.resize(options.max_payload_size, 0);
+
+        Chunk {
+            payload: padded,
+            payload_length,
+            span,
+            options,
+            stamp,
+            address: OnceLock::new(),
+        }
+    }
+
+    /// The payload, zero padded to the maximum payload size.
+    pub fn data(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn max_payload_length(&self) -> usize {
+        self.options.max_payload_size
+    }
+
+    pub fn add_stamp(&mut self, stamp: Vec<u8>) {
+        self.stamp = Some(stamp);
+    }
+
+    pub fn stamp(&self) -> Option<Vec<u8>> {
+        self.stamp.clone()
+    }
+
+    pub fn bmt_root(&self) -> Vec<u8> {
+        self.bmt_levels().pop().unwrap().remove(0)
+    }
+
+    pub fn address(&self) -> [u8; 32] {
+        *self.address.get_or_init(|| {
+            let mut data = self.span.to_bytes().to_vec();
+            data.extend(self.bmt_root());
+            *keccak256(data)
+        })
+    }
+
+    /// The sister segments of the segment at `segment_index` on each level of the binary merkle
+    /// tree, from the payload up to the level below the root.
+    pub fn inclusion_proof(&self, mut segment_index: usize) -> Vec<Vec<u8>> {
+        let levels = self.bmt_levels();
+        let mut sister_segments = Vec::with_capacity(levels.len() - 1);
+
+        for level in &levels[..levels.len() - 1] {
+            sister_segments.push(level[segment_index ^ 1].clone());
+            segment_index /= 2;
+        }
+
+        sister_segments
+    }
+
+    // the levels of the binary merkle tree over the payload segments, ending with the root level
+    fn bmt_levels(&self) -> Vec<Vec<Vec<u8>>> {
+        let mut level: Vec<Vec<u8>> = self
+            .payload
+            .chunks(SEGMENT_SIZE)
+            .map(|segment| segment.to_vec())
+            .collect();
+        let mut levels = vec![level.clone()];
+
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| keccak256(pair.concat()).to_vec())
+                .collect();
+            levels.push(level.clone());
+        }
+
+        levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_payload() {
+        let chunk = Chunk::new(b"hello", None, Options::default(), None);
+
+        assert_eq!(chunk.payload_length, 5);
+        assert_eq!(chunk.span().value(), 5);
+        assert_eq!(chunk.data().len(), DEFAULT_MAX_PAYLOAD_SIZE);
+        assert_eq!(&chunk.data()[..5], b"hello");
+    }
+
+    #[test]
+    fn inclusion_proof_reaches_root() {
+        let chunk = Chunk::new(&[7; 100], None, Options::default(), None);
+        let proof = chunk.inclusion_proof(3);
+        assert_eq!(proof.len(), 7);
+
+        let mut segment = chunk.data()[3 * SEGMENT_SIZE..4 * SEGMENT_SIZE].to_vec();
+        let mut index = 3;
+        for sister in proof {
+            segment = match index % 2 {
+                0 => keccak256([segment, sister].concat()).to_vec(),
+                _ => keccak256([sister, segment].concat()).to_vec(),
+            };
+            index /= 2;
+        }
+        assert_eq!(segment, chunk.bmt_root());
+    }
+}
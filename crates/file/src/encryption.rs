@@ -0,0 +1,143 @@
+use alloy::primitives::keccak256;
+use rand::RngCore;
+
+use super::SEGMENT_SIZE;
+
+/// Length of the symmetric key used to encrypt a chunk.
+pub const KEY_LENGTH: usize = 32;
+
+/// Length of an encrypted reference: the chunk address followed by its decryption key.
+pub const ENCRYPTED_REFERENCE_SIZE: usize = SEGMENT_SIZE + KEY_LENGTH;
+
+pub type Key = [u8; KEY_LENGTH];
+
+#[derive(Debug, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("Data length {length} exceeds padding length {padding}")]
+    DataTooLong { length: usize, padding: usize },
+}
+
+/// Generate a random key for encrypting a single chunk.
+pub fn generate_key() -> Key {
+    let mut key = [0u8; KEY_LENGTH];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+/// Counter-mode keccak encryption as used by Swarm for chunk encryption.
+///
+/// Every `KEY_LENGTH` sized segment of the input is XORed with a segment key derived as
+/// `keccak256(keccak256(key || ctr))`, where `ctr` is the little-endian `u32` segment index
+/// offset by `init_ctr`. As XOR is symmetric, the same transform both encrypts and decrypts.
+pub struct Encryption {
+    key: Key,
+    padding: usize,
+    init_ctr: u32,
+}
+
+impl Encryption {
+    pub fn new(key: Key, padding: usize, init_ctr: u32) -> Encryption {
+        Encryption {
+            key,
+            padding,
+            init_ctr,
+        }
+    }
+
+    /// Encryption for the span of a chunk. The span counter starts after the last reference
+    /// that an intermediate chunk may hold.
+    pub fn span(key: Key, max_payload_size: usize) -> Encryption {
+        Self::new(key, 0, (max_payload_size / ENCRYPTED_REFERENCE_SIZE) as u32)
+    }
+
+    /// Encryption for the payload of a chunk, padding the payload to `max_payload_size`.
+    pub fn data(key: Key, max_payload_size: usize) -> Encryption {
+        Self::new(key, max_payload_size, 0)
+    }
+
+    /// Encrypt `data`, padding the output with random bytes up to the padding length if set.
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        if self.padding > 0 && data.len() > self.padding {
+            return Err(EncryptionError::DataTooLong {
+                length: data.len(),
+                padding: self.padding,
+            });
+        }
+
+        let mut out = vec![0u8; data.len().max(self.padding)];
+        self.transform(data, &mut out);
+        rand::thread_rng().fill_bytes(&mut out[data.len()..]);
+
+        Ok(out)
+    }
+
+    /// Decrypt `data`. The output is the same length as the input, including any padding.
+    pub fn decrypt(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0u8; data.len()];
+        self.transform(data, &mut out);
+
+        out
+    }
+
+    fn transform(&self, input: &[u8], out: &mut [u8]) {
+        for (i, segment) in input.chunks(KEY_LENGTH).enumerate() {
+            let segment_key = self.segment_key(self.init_ctr + i as u32);
+            let offset = i * KEY_LENGTH;
+
+            for (j, byte) in segment.iter().enumerate() {
+                out[offset + j] = byte ^ segment_key[j];
+            }
+        }
+    }
+
+    fn segment_key(&self, ctr: u32) -> Key {
+        let ctr_hash = keccak256([&self.key[..], &ctr.to_le_bytes()[..]].concat());
+
+        let mut segment_key = [0u8; KEY_LENGTH];
+        segment_key.copy_from_slice(&keccak256(&ctr_hash[..])[..]);
+        segment_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let key = [7u8; KEY_LENGTH];
+        let data = b"hello swarm, this payload spans more than one segment".to_vec();
+
+        let encrypted = Encryption::data(key, 4096).encrypt(&data).unwrap();
+        assert_eq!(encrypted.len(), 4096);
+        assert_ne!(&encrypted[..data.len()], &data[..]);
+
+        let decrypted = Encryption::data(key, 4096).decrypt(&encrypted);
+        assert_eq!(&decrypted[..data.len()], &data[..]);
+    }
+
+    #[test]
+    fn span_and_data_use_distinct_counters() {
+        let key = [7u8; KEY_LENGTH];
+        let span = 42u64.to_le_bytes();
+
+        let as_span = Encryption::span(key, 4096).encrypt(&span).unwrap();
+        let as_data = Encryption::new(key, 0, 0).encrypt(&span).unwrap();
+
+        assert_eq!(as_span.len(), span.len());
+        assert_ne!(as_span, as_data);
+    }
+
+    #[test]
+    fn rejects_data_exceeding_padding() {
+        let key = [7u8; KEY_LENGTH];
+
+        assert!(matches!(
+            Encryption::data(key, 64).encrypt(&[1u8; 65]),
+            Err(EncryptionError::DataTooLong {
+                length: 65,
+                padding: 64
+            })
+        ));
+    }
+}
@@ -1,24 +1,52 @@
-use std::io::{BufRead, BufReader, Cursor};
-
-use alloy_primitives::keccak256;
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io::{BufRead, BufReader, Cursor, Read},
+    sync::{Arc, OnceLock},
+};
 
-// use super::keccak256;
+use alloy::primitives::keccak256;
+use nectar_primitives_traits::BzzReference;
 
 use super::{
     chunk::{Chunk, Options},
+    encryption::{generate_key, Encryption, EncryptionError, Key, ENCRYPTED_REFERENCE_SIZE},
     span::Span,
     SEGMENT_SIZE,
 };
 
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("Chunk {} not found", alloy::primitives::hex::encode(.0))]
+    ChunkNotFound([u8; 32]),
+    #[error("Span mismatch (root span: {expected}, leaf spans: {actual})")]
+    SpanMismatch { expected: u64, actual: u64 },
+    #[error("Empty file")]
+    EmptyFile,
+    #[error("Invalid span {0}")]
+    InvalidSpan(u64),
+    #[error("Invalid encrypted reference length {0}")]
+    InvalidReferenceLength(usize),
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
 pub struct ChunkInclusionProof {
     span: Span,
     sister_segments: Vec<Vec<u8>>,
 }
 
+/// Called with the bytes chunked so far and the total size of the file.
+pub type Progress = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
 pub struct ChunkedFile {
     payload: Vec<u8>,
     span: Span,
     options: Options,
+    progress: Option<Progress>,
+    // the file is split once, on first use
+    leaf_chunks: OnceLock<Vec<Chunk>>,
     // reader: &'a mut BufReader<R>,
 }
 
@@ -30,34 +58,54 @@ impl ChunkedFile {
             payload,
             span: Span::new(payload_length as u64),
             options,
+            progress: None,
+            leaf_chunks: OnceLock::new(),
         }
     }
 
-    // splitter
+    /// Report progress to `progress` after each leaf chunk is produced. The file is split once,
+    /// when its chunks are first needed, eg. to compute its address or all of its chunks.
+    pub fn with_progress(mut self, progress: Progress) -> ChunkedFile {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// The contents of the file.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
     pub fn leaf_chunks(&self) -> Vec<Chunk> {
+        self.leaf_chunks.get_or_init(|| self.split()).clone()
+    }
+
+    // splitter
+    fn split(&self) -> Vec<Chunk> {
         let mut reader =
             BufReader::with_capacity(self.options.max_payload_size, Cursor::new(&self.payload));
 
         let mut chunks: Vec<Chunk> = Vec::<Chunk>::new();
+        let total = self.payload.len() as u64;
+        let mut done = 0;
 
         // dump loop for chunking out the buffer
         loop {
             let chunk_length = {
                 match reader.fill_buf() {
                     Ok(t) => {
-                        let mut chunk_payload = Vec::from(t);
+                        let chunk_payload = Vec::from(t);
                         let chunk_payload_length = chunk_payload.len();
 
                         if chunk_payload_length == 0 {
                             break;
                         }
 
-                        chunks.push(Chunk::new(
-                            &mut chunk_payload,
-                            None,
-                            Options::default(),
-                            None,
-                        ));
+                        chunks.push(Chunk::new(&chunk_payload, None, Options::default(), None));
+
+                        done += chunk_payload_length as u64;
+                        if let Some(progress) = &self.progress {
+                            progress(done, total);
+                        }
 
                         chunk_payload_length
                     }
@@ -71,10 +119,352 @@ impl ChunkedFile {
         chunks
     }
 
+    /// Returns the distinct addresses of the leaf chunks. As chunks are content addressed, any
+    /// repeated leaf shares its address with an earlier one and only needs to be uploaded once.
+    pub fn unique_leaf_addresses(&self) -> HashSet<[u8; 32]> {
+        self.leaf_chunks().iter().map(|c| c.address()).collect()
+    }
+
     pub fn address(&self) -> [u8; 32] {
         Self::bmt_root_chunk(&mut self.leaf_chunks()).address()
     }
 
+    /// The plain reference of the file, see [`ChunkedFile::encrypt`] for an encrypted one.
+    pub fn reference(&self) -> BzzReference {
+        BzzReference::Plain(self.address().into())
+    }
+
+    /// Compute the file address of everything read from `reader` without holding the file in
+    /// memory.
+    ///
+    /// Leaf chunks are hashed as they are read and only their addresses are kept, folding each
+    /// full set of references into its parent level, so that at most one intermediate chunk's
+    /// worth of references is retained per tree level.
+    pub fn hash_reader(reader: impl Read, options: Options) -> Result<[u8; 32], FileError> {
+        Self::hash_reader_inner(reader, options, None)
+    }
+
+    /// As [`ChunkedFile::hash_reader`], reporting progress to `progress` after each leaf chunk
+    /// is hashed. `total` is passed through as the total size, eg. the length of the file being
+    /// read, as it can't be known from the reader.
+    pub fn hash_reader_with_progress(
+        reader: impl Read,
+        options: Options,
+        total: u64,
+        progress: Progress,
+    ) -> Result<[u8; 32], FileError> {
+        Self::hash_reader_inner(reader, options, Some((total, progress)))
+    }
+
+    fn hash_reader_inner(
+        mut reader: impl Read,
+        options: Options,
+        progress: Option<(u64, Progress)>,
+    ) -> Result<[u8; 32], FileError> {
+        let max_payload_size = options.max_payload_size;
+        let branches = max_payload_size / SEGMENT_SIZE;
+
+        let mut levels: Vec<Vec<([u8; 32], u64)>> = vec![Vec::with_capacity(branches)];
+        let mut done = 0;
+
+        loop {
+            let mut payload = Vec::<u8>::with_capacity(max_payload_size);
+            (&mut reader)
+                .take(max_payload_size as u64)
+                .read_to_end(&mut payload)?;
+
+            if payload.is_empty() {
+                break;
+            }
+
+            let chunk = Chunk::new(&payload, None, Options::default(), None);
+            Self::push_reference(
+                &mut levels,
+                0,
+                (chunk.address(), chunk.span().value()),
+                branches,
+            );
+
+            done += payload.len() as u64;
+            if let Some((total, progress)) = &progress {
+                progress(done, *total);
+            }
+        }
+
+        if levels.len() == 1 && levels[0].is_empty() {
+            return Err(FileError::EmptyFile);
+        }
+
+        // fold the remaining references bottom-up. as with carrier chunks, a lone reference is
+        // moved up to its parent level rather than being wrapped in an intermediate chunk
+        let mut level = 0;
+        loop {
+            let has_parent = levels[level + 1..].iter().any(|l| !l.is_empty());
+            let references = std::mem::take(&mut levels[level]);
+
+            match references.len() {
+                0 => {}
+                1 if !has_parent => return Ok(references[0].0),
+                1 => Self::push_reference(&mut levels, level + 1, references[0], branches),
+                _ => Self::push_reference(
+                    &mut levels,
+                    level + 1,
+                    Self::wrap_references(&references),
+                    branches,
+                ),
+            }
+
+            level += 1;
+        }
+    }
+
+    fn push_reference(
+        levels: &mut Vec<Vec<([u8; 32], u64)>>,
+        level: usize,
+        reference: ([u8; 32], u64),
+        branches: usize,
+    ) {
+        if levels.len() == level {
+            levels.push(Vec::with_capacity(branches));
+        }
+
+        levels[level].push(reference);
+
+        if levels[level].len() == branches {
+            let references = std::mem::take(&mut levels[level]);
+            Self::push_reference(
+                levels,
+                level + 1,
+                Self::wrap_references(&references),
+                branches,
+            );
+        }
+    }
+
+    fn wrap_references(references: &[([u8; 32], u64)]) -> ([u8; 32], u64) {
+        let addresses: Vec<u8> = references.iter().flat_map(|(a, _)| *a).collect();
+        let span = references.iter().map(|(_, s)| s).sum();
+
+        let chunk = Chunk::new(&addresses, Some(span), Options::default(), None);
+
+        (chunk.address(), span)
+    }
+
+    /// Reconstruct a file from the address of its root chunk and the chunks of its tree.
+    ///
+    /// The spans of the leaf chunks reachable from the root must add up to the root span,
+    /// otherwise the tree is truncated or malformed and [`FileError::SpanMismatch`] is returned.
+    pub fn from_root(
+        root: [u8; 32],
+        chunks: &[Chunk],
+        options: Options,
+    ) -> Result<ChunkedFile, FileError> {
+        let chunks: HashMap<[u8; 32], &Chunk> = chunks.iter().map(|c| (c.address(), c)).collect();
+        let root_chunk = chunks.get(&root).ok_or(FileError::ChunkNotFound(root))?;
+
+        let mut payload = Vec::<u8>::new();
+        Self::join_into(root_chunk, &chunks, &mut payload)?;
+
+        let expected = root_chunk.span().value();
+        if payload.len() as u64 != expected {
+            return Err(FileError::SpanMismatch {
+                expected,
+                actual: payload.len() as u64,
+            });
+        }
+
+        Ok(ChunkedFile::new(payload, options))
+    }
+
+    /// Reconstruct a file from its reference and the chunks of its tree, decrypting it if the
+    /// reference is encrypted.
+    pub fn join(
+        reference: &BzzReference,
+        chunks: &[Chunk],
+        options: Options,
+    ) -> Result<ChunkedFile, FileError> {
+        match reference {
+            BzzReference::Plain(address) => Self::from_root(address.0, chunks, options),
+            BzzReference::Encrypted { .. } => {
+                Ok(ChunkedFile::new(Self::decrypt(reference, chunks)?, options))
+            }
+        }
+    }
+
+    fn join_into(
+        chunk: &Chunk,
+        chunks: &HashMap<[u8; 32], &Chunk>,
+        payload: &mut Vec<u8>,
+    ) -> Result<(), FileError> {
+        let max_payload_size = chunk.max_payload_length();
+        let span = chunk.span().value();
+        let data = chunk.data();
+
+        if span <= max_payload_size as u64 {
+            payload.extend_from_slice(&data[..span as usize]);
+            return Ok(());
+        }
+
+        // intermediate chunk: the span bounds the number of references, an empty reference
+        // slot means the tree is missing children, which the root span check reports
+        let branch_size = branch_size(span, max_payload_size, SEGMENT_SIZE)?;
+
+        let reference_count = span.div_ceil(branch_size) as usize;
+        for reference in data[..reference_count * SEGMENT_SIZE].chunks(SEGMENT_SIZE) {
+            if reference.iter().all(|b| *b == 0) {
+                break;
+            }
+
+            let address: [u8; 32] = reference.try_into().unwrap();
+            let child = chunks
+                .get(&address)
+                .ok_or(FileError::ChunkNotFound(address))?;
+            Self::join_into(child, chunks, payload)?;
+        }
+
+        Ok(())
+    }
+
+    /// Split the file into encrypted chunks, each encrypted with its own random key.
+    ///
+    /// Returns every encrypted chunk, leaves first and the root chunk last, along with the
+    /// encrypted reference (root chunk address followed by its key) from which the file can be
+    /// decrypted. As intermediate chunks hold 64-byte references, they have half the branching
+    /// factor of unencrypted intermediate chunks.
+    pub fn encrypt(&self) -> (Vec<Chunk>, BzzReference) {
+        let max_payload_size = self.options.max_payload_size;
+        let branches = max_payload_size / ENCRYPTED_REFERENCE_SIZE;
+
+        let mut chunks = Vec::<Chunk>::new();
+
+        // leaf level, holding the reference and span of each chunk
+        let mut level: Vec<(Vec<u8>, u64)> = self
+            .payload
+            .chunks(max_payload_size)
+            .map(|data| {
+                let (chunk, reference) = self.encrypt_chunk(data, data.len() as u64);
+                chunks.push(chunk);
+                (reference, data.len() as u64)
+            })
+            .collect();
+
+        let pop_carrier = |level: &mut Vec<(Vec<u8>, u64)>| match level.len() % branches {
+            1 if level.len() > 1 => level.pop(),
+            _ => None,
+        };
+
+        let mut carrier = pop_carrier(&mut level);
+
+        while level.len() != 1 || carrier.is_some() {
+            let mut next_level = Vec::new();
+
+            for children in level.chunks(branches) {
+                let data: Vec<u8> = children.iter().flat_map(|(r, _)| r.clone()).collect();
+                let span = children.iter().map(|(_, s)| s).sum();
+
+                let (chunk, reference) = self.encrypt_chunk(&data, span);
+                chunks.push(chunk);
+                next_level.push((reference, span));
+            }
+
+            // edge case handling when there is a carrier chunk
+            carrier = match carrier {
+                Some(c) if next_level.len() % branches != 0 => {
+                    next_level.push(c);
+                    None
+                }
+                Some(c) => Some(c),
+                None => pop_carrier(&mut next_level),
+            };
+
+            level = next_level;
+        }
+
+        let reference = level.pop().unwrap().0;
+        (
+            chunks,
+            BzzReference::try_from(reference.as_slice()).unwrap(),
+        )
+    }
+
+    /// Reassemble and decrypt a file given its encrypted reference and its chunks, as returned
+    /// by [`ChunkedFile::encrypt`].
+    ///
+    /// Returns [`FileError::InvalidReferenceLength`] if the reference is not encrypted, and
+    /// [`FileError::ChunkNotFound`] if a chunk of the tree is missing. A wrong key decrypts
+    /// spans at random, which usually ends in one of these or [`FileError::InvalidSpan`].
+    pub fn decrypt(reference: &BzzReference, chunks: &[Chunk]) -> Result<Vec<u8>, FileError> {
+        let chunks: HashMap<[u8; 32], &Chunk> = chunks.iter().map(|c| (c.address(), c)).collect();
+
+        let mut payload = Vec::<u8>::new();
+        Self::decrypt_into(&reference.to_vec(), &chunks, &mut payload)?;
+
+        Ok(payload)
+    }
+
+    fn decrypt_into(
+        reference: &[u8],
+        chunks: &HashMap<[u8; 32], &Chunk>,
+        payload: &mut Vec<u8>,
+    ) -> Result<(), FileError> {
+        if reference.len() != ENCRYPTED_REFERENCE_SIZE {
+            return Err(FileError::InvalidReferenceLength(reference.len()));
+        }
+
+        let (address, key) = reference.split_at(SEGMENT_SIZE);
+        let address: [u8; 32] = address.try_into().unwrap();
+        let key: Key = key.try_into().unwrap();
+        let chunk = chunks
+            .get(&address)
+            .ok_or(FileError::ChunkNotFound(address))?;
+
+        let max_payload_size = chunk.max_payload_length();
+        let span = Encryption::span(key, max_payload_size).decrypt(&chunk.span().to_bytes());
+        let span = u64::from_le_bytes(span.try_into().unwrap());
+        let data = Encryption::data(key, max_payload_size).decrypt(&chunk.data());
+
+        if span <= max_payload_size as u64 {
+            payload.extend_from_slice(&data[..span as usize]);
+            return Ok(());
+        }
+
+        // intermediate chunk: find how many bytes each child reference carries so that the
+        // number of references can be derived from the span, skipping the random padding
+        let branch_size = branch_size(span, max_payload_size, ENCRYPTED_REFERENCE_SIZE)?;
+
+        let reference_count = span.div_ceil(branch_size) as usize;
+        for child in
+            data[..reference_count * ENCRYPTED_REFERENCE_SIZE].chunks(ENCRYPTED_REFERENCE_SIZE)
+        {
+            Self::decrypt_into(child, chunks, payload)?;
+        }
+
+        Ok(())
+    }
+
+    fn encrypt_chunk(&self, data: &[u8], span: u64) -> (Chunk, Vec<u8>) {
+        let max_payload_size = self.options.max_payload_size;
+        let key = generate_key();
+
+        // neither the span nor the data of a chunk exceed its payload, so encryption can't fail
+        let encrypted_span = Encryption::span(key, max_payload_size)
+            .encrypt(&span.to_le_bytes())
+            .expect("span fits the payload");
+        let encrypted_data = Encryption::data(key, max_payload_size)
+            .encrypt(data)
+            .expect("data fits the payload");
+
+        let chunk = Chunk::new(
+            &encrypted_data,
+            Some(u64::from_le_bytes(encrypted_span.try_into().unwrap())),
+            Options::default(),
+            None,
+        );
+        let reference = [chunk.address().to_vec(), key.to_vec()].concat();
+
+        (chunk, reference)
+    }
+
     pub fn file_inclusion_proof_bottom_up(
         &self,
         mut segment_index: usize,
@@ -109,7 +499,7 @@ impl ChunkedFile {
                 segment_index >>= chunk_bmt_levels; // log2(128) -> skip this level check now
                 loop {
                     let (next_level_chunks, next_level_carrier_chunk) =
-                        Self::next_bmt_level(&mut level_chunks, carrier_chunk);
+                        Self::next_bmt_level(&level_chunks, carrier_chunk);
                     level_chunks = next_level_chunks;
                     carrier_chunk = next_level_carrier_chunk;
 
@@ -132,7 +522,7 @@ impl ChunkedFile {
             segment_index = chunk_index_for_proof;
 
             let (next_level_chunks, next_level_carrier_chunk) =
-                Self::next_bmt_level(&mut level_chunks, carrier_chunk);
+                Self::next_bmt_level(&level_chunks, carrier_chunk);
             level_chunks = next_level_chunks;
             carrier_chunk = next_level_carrier_chunk;
         }
@@ -169,32 +559,27 @@ impl ChunkedFile {
             for proof_segment in prove_chunk.sister_segments {
                 calculated_hash = match prove_segment_index % 2 == 0 {
                     true => keccak256::<Vec<u8>>(
-                        calculated_hash
-                            .into_iter()
-                            .chain(proof_segment.into_iter())
-                            .collect(),
+                        calculated_hash.into_iter().chain(proof_segment).collect(),
                     )
                     .into_iter()
                     .collect(),
                     false => keccak256::<Vec<u8>>(
-                        proof_segment
-                            .into_iter()
-                            .chain(calculated_hash.into_iter())
-                            .collect(),
+                        proof_segment.into_iter().chain(calculated_hash).collect(),
                     )
                     .into_iter()
                     .collect(),
                 };
                 prove_segment_index /= 2;
             }
-            calculated_hash = Vec::<u8>::from(keccak256::<Vec<u8>>(
+            calculated_hash = keccak256::<Vec<u8>>(
                 prove_chunk
                     .span
                     .to_bytes()
                     .into_iter()
-                    .chain(calculated_hash.into_iter())
+                    .chain(calculated_hash)
                     .collect(),
-            ));
+            )
+            .to_vec();
             // this line is necessary if the prove_segment_index
             // was in a carrier chunk
             prove_segment_index = parent_chunk_index as usize;
@@ -244,10 +629,9 @@ impl ChunkedFile {
         level_chunks.push(leaf_chunks.to_vec());
 
         while level_chunks[level_chunks.len() - 1].len() != 1 {
-            eprintln!("Passing level {}", level_chunks.len());
             let level_chunks_length = level_chunks.len();
             let (next_level_chunks, next_level_carrier_chunk) =
-                Self::next_bmt_level(&mut level_chunks[level_chunks_length - 1], carrier_chunk);
+                Self::next_bmt_level(&level_chunks[level_chunks_length - 1], carrier_chunk);
 
             carrier_chunk = next_level_carrier_chunk;
             level_chunks.push(next_level_chunks);
@@ -256,6 +640,13 @@ impl ChunkedFile {
         level_chunks
     }
 
+    /// Returns every chunk of the file: the leaf chunks followed by each level of intermediate
+    /// chunks, bottom-up. The last chunk returned is always the root chunk, whose address is the
+    /// file address.
+    pub fn all_chunks(&self) -> Vec<Chunk> {
+        self.bmt().into_iter().flatten().collect()
+    }
+
     pub fn bmt_root_chunk(chunks: &mut Vec<Chunk>) -> Chunk {
         let chunks_length = chunks.len();
 
@@ -276,7 +667,7 @@ impl ChunkedFile {
     }
 
     pub fn next_bmt_level(
-        chunks: &mut Vec<Chunk>,
+        chunks: &[Chunk],
         carrier_chunk: Option<Chunk>,
     ) -> (Vec<Chunk>, Option<Chunk>) {
         let chunks_length = chunks.len();
@@ -326,7 +717,7 @@ impl ChunkedFile {
     pub fn create_intermediate_chunk(chunks: &mut [Chunk], options: Options) -> Chunk {
         // use a fold instead of a map to avoid cloning the chunk addresses
         let n = chunks.len();
-        let (mut chunk_addresses, chunk_span_sum_values) = chunks
+        let (chunk_addresses, chunk_span_sum_values) = chunks
             .iter_mut()
             .map(|f| (f.address(), f.span().value()))
             .fold(
@@ -337,12 +728,7 @@ impl ChunkedFile {
                 },
             );
 
-        Chunk::new(
-            &mut chunk_addresses,
-            Some(chunk_span_sum_values),
-            options,
-            None,
-        )
+        Chunk::new(&chunk_addresses, Some(chunk_span_sum_values), options, None)
     }
 
     pub fn pop_carrier_chunk(chunks: &mut Vec<Chunk>) -> Option<Chunk> {
@@ -379,6 +765,29 @@ impl ChunkedFile {
     // }
 }
 
+// the number of bytes carried by each reference of an intermediate chunk spanning `span`, for
+// references of `reference_size` bytes. A span beyond the largest tree that can be addressed,
+// as read from a corrupt chunk, is rejected rather than overflowing.
+fn branch_size(
+    span: u64,
+    max_payload_size: usize,
+    reference_size: usize,
+) -> Result<u64, FileError> {
+    let branches = (max_payload_size / reference_size) as u64;
+    if branches < 2 {
+        return Err(FileError::InvalidSpan(span));
+    }
+
+    let mut branch_size = max_payload_size as u64;
+    while branch_size < span {
+        branch_size = branch_size
+            .checked_mul(branches)
+            .ok_or(FileError::InvalidSpan(span))?;
+    }
+
+    Ok(branch_size / branches)
+}
+
 // impl<'a, R> Iterator for ChunkedFile<'a, R>
 // where
 //     R: Read,
@@ -403,18 +812,99 @@ impl ChunkedFile {
 //     }
 // }
 
+/// The chunks of a file that have been stored so far, so that an interrupted upload can be
+/// resumed without sending them again.
+///
+/// The state can be persisted with [`UploadState::to_bytes`] between attempts. Chunks are
+/// content addressed, so the state of one file can be resumed against any upload of the same
+/// content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadState {
+    sent: BTreeSet<[u8; 32]>,
+}
+
+impl UploadState {
+    pub fn new() -> UploadState {
+        UploadState::default()
+    }
+
+    /// Record the chunk at `address` as stored.
+    pub fn mark_sent(&mut self, address: [u8; 32]) {
+        self.sent.insert(address);
+    }
+
+    pub fn is_sent(&self, address: &[u8; 32]) -> bool {
+        self.sent.contains(address)
+    }
+
+    /// Returns the chunks of `file` that have not been stored yet, in the order of
+    /// [`ChunkedFile::all_chunks`], each distinct chunk only once.
+    pub fn remaining(&self, file: &ChunkedFile) -> Vec<Chunk> {
+        let mut seen = HashSet::new();
+        file.all_chunks()
+            .into_iter()
+            .filter(|c| {
+                let address = c.address();
+                !self.sent.contains(&address) && seen.insert(address)
+            })
+            .collect()
+    }
+
+    /// Push every chunk of `file` that has not been stored yet, recording each one as sent once
+    /// `push` succeeds. Stops at the first error, keeping the chunks sent until then, and
+    /// returns the number of chunks pushed otherwise.
+    pub fn resume<E>(
+        &mut self,
+        file: &ChunkedFile,
+        mut push: impl FnMut(&Chunk) -> Result<(), E>,
+    ) -> Result<usize, E> {
+        let remaining = self.remaining(file);
+        for chunk in &remaining {
+            push(chunk)?;
+            self.mark_sent(chunk.address());
+        }
+
+        Ok(remaining.len())
+    }
+
+    /// Serialise the state as the concatenated addresses of the sent chunks, in ascending order
+    /// so that the same state always serialises to the same bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.sent.iter().flatten().copied().collect()
+    }
+
+    /// Restore a state serialised by [`UploadState::to_bytes`], returning `None` if `bytes` is
+    /// not a whole number of addresses.
+    pub fn from_bytes(bytes: &[u8]) -> Option<UploadState> {
+        if bytes.len() % 32 != 0 {
+            return None;
+        }
+
+        Some(UploadState {
+            sent: bytes
+                .chunks_exact(32)
+                .map(|a| a.try_into().unwrap())
+                .collect(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs::File, io::Read};
 
     use super::*;
-    use alloy_primitives::hex::ToHexExt;
+    use alloy::primitives::hex::ToHexExt;
 
     const EXPECTED_SPAN: [u8; 8] = [3, 0, 0, 0, 0, 0, 0, 0];
 
     fn setup_carrier_chunk_file() -> (Vec<u8>, usize) {
         // edge case - carrier chunk
-        let mut f = File::open("./test-files/carrier-chunk-blob").unwrap();
+        let mut f = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../primitives/test-files/carrier-chunk-blob"
+        ))
+        .unwrap();
         let mut payload = Vec::<u8>::new();
 
         f.read_to_end(&mut payload).ok();
@@ -426,7 +916,11 @@ mod tests {
 
     fn setup_carrier_chunk_file_2() -> (Vec<u8>, usize) {
         // edge case - carrier chunk - intermediate level
-        let mut f = File::open("./test-files/carrier-chunk-blob-2").unwrap();
+        let mut f = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../primitives/test-files/carrier-chunk-blob-2"
+        ))
+        .unwrap();
         let mut payload = Vec::<u8>::new();
 
         f.read_to_end(&mut payload).ok();
@@ -437,7 +931,11 @@ mod tests {
     }
 
     fn setup_bos_chunk_file() -> (Vec<u8>, usize) {
-        let mut f = File::open("./test-files/bos.pdf").unwrap();
+        let mut f = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../primitives/test-files/bos.pdf"
+        ))
+        .unwrap();
         let mut payload = Vec::<u8>::new();
 
         f.read_to_end(&mut payload).ok();
@@ -511,6 +1009,274 @@ mod tests {
         );
     }
 
+    #[test]
+    fn all_chunks_include_intermediates() {
+        // 600 KiB spans 150 leaf chunks, referenced by two intermediate chunks under the root
+        let payload = vec![0xab; 600 * 1024];
+
+        let chunked_file = ChunkedFile::new(payload, Options::default());
+        let leaf_chunks = chunked_file.leaf_chunks();
+        let all_chunks = chunked_file.all_chunks();
+
+        assert_eq!(leaf_chunks.len(), 150);
+        assert_eq!(all_chunks.len(), 150 + 2 + 1);
+
+        // the root chunk is last
+        let file_address = chunked_file.address();
+        assert_eq!(all_chunks.last().unwrap().address(), file_address);
+        assert!(all_chunks.iter().any(|c| c.address() == file_address));
+    }
+
+    #[test]
+    fn repeated_leaves_share_addresses() {
+        // four blocks, two of which repeat the first one
+        let payload = [
+            vec![1u8; 4096],
+            vec![2u8; 4096],
+            vec![1u8; 4096],
+            vec![1u8; 4096],
+        ]
+        .concat();
+        let chunked_file = ChunkedFile::new(payload, Options::default());
+
+        let unique = chunked_file.unique_leaf_addresses();
+        let leaves = chunked_file.leaf_chunks();
+
+        assert_eq!(leaves.len(), 4);
+        assert_eq!(
+            unique,
+            HashSet::from([leaves[0].address(), leaves[1].address()])
+        );
+    }
+
+    #[test]
+    fn hash_reader_matches_address() {
+        let (carrier_payload, _) = setup_carrier_chunk_file();
+        let large_payload: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+        for payload in [
+            vec![1, 2, 3],
+            vec![0xab; 128 * 4096],
+            vec![0xab; 129 * 4096],
+            carrier_payload,
+            large_payload,
+        ] {
+            let address = ChunkedFile::new(payload.clone(), Options::default()).address();
+
+            assert_eq!(
+                ChunkedFile::hash_reader(Cursor::new(payload), Options::default()).unwrap(),
+                address
+            );
+        }
+    }
+
+    #[test]
+    fn hash_reader_streams_files() {
+        // a carrier chunk on an intermediate level
+        let (payload, _) = setup_carrier_chunk_file_2();
+        assert_eq!(
+            ChunkedFile::hash_reader(Cursor::new(&payload), Options::default()).unwrap(),
+            ChunkedFile::new(payload, Options::default()).address()
+        );
+
+        // read straight from disk, matching the reference bee computes for the file
+        let file = File::open(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../primitives/test-files/bos.pdf"
+        ))
+        .unwrap();
+        assert_eq!(
+            ChunkedFile::hash_reader(file, Options::default())
+                .unwrap()
+                .encode_hex(),
+            "b8d17f296190ccc09a2c36b7a59d0f23c4479a3958c3bb02dc669466ec919c5d"
+        );
+    }
+
+    #[test]
+    fn hash_reader_handles_short_reads() {
+        // yields at most 1000 bytes per read, so chunks span several reads
+        struct Trickle(Cursor<Vec<u8>>);
+
+        impl Read for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let len = buf.len().min(1000);
+                self.0.read(&mut buf[..len])
+            }
+        }
+
+        let payload: Vec<u8> = (0..300 * 4096 + 7).map(|i| (i % 251) as u8).collect();
+        let address = ChunkedFile::new(payload.clone(), Options::default()).address();
+
+        assert_eq!(
+            ChunkedFile::hash_reader(Trickle(Cursor::new(payload)), Options::default()).unwrap(),
+            address
+        );
+        assert!(matches!(
+            ChunkedFile::hash_reader(Cursor::new(Vec::new()), Options::default()),
+            Err(FileError::EmptyFile)
+        ));
+    }
+
+    #[test]
+    fn from_root_round_trip() {
+        let (payload, _) = setup_carrier_chunk_file();
+        let chunked_file = ChunkedFile::new(payload.clone(), Options::default());
+
+        let joined = ChunkedFile::from_root(
+            chunked_file.address(),
+            &chunked_file.all_chunks(),
+            Options::default(),
+        )
+        .unwrap();
+
+        assert_eq!(joined.payload, payload);
+    }
+
+    #[test]
+    fn from_root_rejects_tree_missing_a_leaf() {
+        let chunked_file = ChunkedFile::new(vec![0xab; 3 * 4096], Options::default());
+        let mut leaves = chunked_file.leaf_chunks();
+
+        // root keeps the full span but only references the first two leaves
+        let addresses: Vec<u8> = leaves[..2].iter().flat_map(|c| c.address()).collect();
+        let root = Chunk::new(&addresses, Some(3 * 4096), Options::default(), None);
+        leaves.push(root.clone());
+
+        match ChunkedFile::from_root(root.address(), &leaves, Options::default()) {
+            Err(FileError::SpanMismatch { expected, actual }) => {
+                assert_eq!(expected, 3 * 4096);
+                assert_eq!(actual, 2 * 4096);
+            }
+            _ => panic!("expected a span mismatch"),
+        }
+    }
+
+    #[test]
+    fn from_root_rejects_incomplete_trees() {
+        let payload: Vec<u8> = (0..3 * 4096 + 1).map(|i| (i % 251) as u8).collect();
+        let chunked_file = ChunkedFile::new(payload, Options::default());
+        let leaves = chunked_file.leaf_chunks();
+        let addresses: Vec<u8> = leaves.iter().flat_map(|c| c.address()).collect();
+
+        // root spanning one byte more than its leaves
+        let root = Chunk::new(&addresses, Some(3 * 4096 + 2), Options::default(), None);
+        let chunks = [leaves.clone(), vec![root.clone()]].concat();
+        assert!(matches!(
+            ChunkedFile::from_root(root.address(), &chunks, Options::default()),
+            Err(FileError::SpanMismatch {
+                expected: 12290,
+                actual: 12289
+            })
+        ));
+
+        // a referenced leaf that isn't among the chunks
+        let chunks = [leaves[1..].to_vec(), chunked_file.bmt().pop().unwrap()].concat();
+        assert!(matches!(
+            ChunkedFile::from_root(chunked_file.address(), &chunks, Options::default()),
+            Err(FileError::ChunkNotFound(address)) if address == leaves[0].address()
+        ));
+    }
+
+    #[test]
+    fn from_root_rejects_oversized_span() {
+        // a span no tree of 4 KiB chunks can reach
+        let root = Chunk::new(&[0xab; 32], Some(u64::MAX), Options::default(), None);
+        let address = root.address();
+
+        assert!(matches!(
+            ChunkedFile::from_root(address, &[root], Options::default()),
+            Err(FileError::InvalidSpan(u64::MAX))
+        ));
+    }
+
+    #[test]
+    fn encrypted_reference_is_64_bytes() {
+        let chunked_file = ChunkedFile::new(vec![1, 2, 3], Options::default());
+
+        let (chunks, reference) = chunked_file.encrypt();
+
+        assert_eq!(chunks.len(), 1);
+        assert!(reference.is_encrypted());
+        assert_eq!(reference.to_vec().len(), ENCRYPTED_REFERENCE_SIZE);
+        assert_eq!(reference.address(), chunks[0].address());
+    }
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let (payload, _) = setup_carrier_chunk_file();
+
+        // small file, a file with a carrier chunk, and a file with two intermediate levels
+        for payload in [
+            b"hello world".to_vec(),
+            payload[..64 * 4096 + 100].to_vec(),
+            payload.clone(),
+        ] {
+            let chunked_file = ChunkedFile::new(payload.clone(), Options::default());
+
+            let (chunks, reference) = chunked_file.encrypt();
+            assert_eq!(chunks.last().unwrap().address(), reference.address());
+
+            assert_eq!(ChunkedFile::decrypt(&reference, &chunks).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn decrypt_reports_missing_chunks() {
+        let chunked_file = ChunkedFile::new(vec![0xab; 3 * 4096], Options::default());
+        let (mut chunks, reference) = chunked_file.encrypt();
+
+        // drop a leaf, so joining fails as it does for a plain reference
+        let missing = chunks.remove(0).address();
+        assert!(matches!(
+            ChunkedFile::join(&reference, &chunks, Options::default()),
+            Err(FileError::ChunkNotFound(address)) if address == missing
+        ));
+
+        assert!(matches!(
+            ChunkedFile::decrypt(&chunked_file.reference(), &chunks),
+            Err(FileError::InvalidReferenceLength(32))
+        ));
+    }
+
+    #[test]
+    fn decrypt_with_wrong_key() {
+        let chunked_file = ChunkedFile::new(vec![0xab; 3 * 4096], Options::default());
+        let (chunks, reference) = chunked_file.encrypt();
+
+        // the root decrypts to a random span, which must not be trusted to walk the tree
+        let mut wrong = reference.to_vec();
+        wrong[SEGMENT_SIZE..].iter_mut().for_each(|b| *b ^= 0xff);
+        let wrong = BzzReference::try_from(wrong.as_slice()).unwrap();
+
+        assert!(matches!(
+            ChunkedFile::decrypt(&wrong, &chunks),
+            Err(FileError::InvalidSpan(_) | FileError::ChunkNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn join_by_reference() {
+        let payload = b"hello world".repeat(1000);
+        let chunked_file = ChunkedFile::new(payload.clone(), Options::default());
+
+        let reference = chunked_file.reference();
+        assert!(!reference.is_encrypted());
+        let joined =
+            ChunkedFile::join(&reference, &chunked_file.all_chunks(), Options::default()).unwrap();
+        assert_eq!(joined.payload, payload);
+
+        let (chunks, reference) = chunked_file.encrypt();
+        let joined = ChunkedFile::join(&reference, &chunks, Options::default()).unwrap();
+        assert_eq!(joined.payload, payload);
+
+        // references shared as hex, eg. on the command line, join the same way
+        let parsed: BzzReference = reference.to_string().parse().unwrap();
+        assert_eq!(parsed, reference);
+        let joined = ChunkedFile::join(&parsed, &chunks, Options::default()).unwrap();
+        assert_eq!(joined.payload, payload);
+    }
+
     #[test]
     fn find_bmt_position_of_payload_segment_index() {
         let (payload, file_length) = setup_carrier_chunk_file();
@@ -655,6 +1421,96 @@ mod tests {
         // expect(() => testGetFileHash(lastSegmentIndex + 1)).toThrowError(/^The given segment index/)
     }
 
+    #[test]
+    fn upload_state_remaining() {
+        let payload: Vec<u8> = (0..20 * 4096u32).map(|i| (i % 251) as u8).collect();
+        let chunked_file = ChunkedFile::new(payload, Options::default());
+        let all_chunks = chunked_file.all_chunks();
+        let (sent, unsent) = all_chunks.split_at(all_chunks.len() / 2);
+
+        let mut state = UploadState::new();
+        for chunk in sent {
+            state.mark_sent(chunk.address());
+        }
+
+        let addresses = |chunks: &[Chunk]| chunks.iter().map(|c| c.address()).collect::<Vec<_>>();
+        assert_eq!(
+            addresses(&state.remaining(&chunked_file)),
+            addresses(unsent)
+        );
+
+        // the state survives being persisted between attempts, regardless of the order in
+        // which chunks were sent
+        let mut reversed = UploadState::new();
+        for chunk in sent.iter().rev() {
+            reversed.mark_sent(chunk.address());
+        }
+        assert_eq!(reversed.to_bytes(), state.to_bytes());
+        let mut state = UploadState::from_bytes(&state.to_bytes()).unwrap();
+        assert!(UploadState::from_bytes(&[0; 33]).is_none());
+
+        // a failed push keeps what was sent before it
+        let mut pushed = Vec::new();
+        let result = state.resume(&chunked_file, |chunk| {
+            if pushed.len() == 2 {
+                return Err("connection reset");
+            }
+            pushed.push(chunk.address());
+            Ok(())
+        });
+        assert_eq!(result, Err("connection reset"));
+        assert!(pushed.iter().all(|a| state.is_sent(a)));
+
+        // resuming only pushes the rest
+        let mut resumed = Vec::new();
+        let count = state
+            .resume(&chunked_file, |chunk| {
+                resumed.push(chunk.address());
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+        assert_eq!(count, unsent.len() - 2);
+        assert_eq!([pushed, resumed].concat(), addresses(unsent));
+        assert!(state.remaining(&chunked_file).is_empty());
+    }
+
+    #[test]
+    fn reports_progress() {
+        let payload = vec![0xab; 10 * 4096 + 100];
+        let total = payload.len() as u64;
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress = reports.clone();
+        let chunked_file = ChunkedFile::new(payload.clone(), Options::default()).with_progress(
+            Arc::new(move |done, total| progress.lock().unwrap().push((done, total))),
+        );
+
+        // the file is only split once, however often its chunks are needed
+        chunked_file.address();
+        chunked_file.address();
+        chunked_file.all_chunks();
+
+        let check = |reports: &[(u64, u64)]| {
+            assert_eq!(reports.len(), 11);
+            assert!(reports.windows(2).all(|w| w[0].0 <= w[1].0));
+            assert!(reports.iter().all(|(_, t)| *t == total));
+            assert_eq!(reports.last().unwrap().0, total);
+        };
+        check(&reports.lock().unwrap());
+
+        // the streaming hasher reports the same way
+        reports.lock().unwrap().clear();
+        let progress = reports.clone();
+        ChunkedFile::hash_reader_with_progress(
+            Cursor::new(payload),
+            Options::default(),
+            total,
+            Arc::new(move |done, total| progress.lock().unwrap().push((done, total))),
+        )
+        .unwrap();
+        check(&reports.lock().unwrap());
+    }
+
     //#[bench]
     //fn find_bmt_position_of_payload_segment_index_bench(b: &mut Bencher) {
     //    let (payload, file_length) = setup_carrier_chunk_file();
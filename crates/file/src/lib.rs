@@ -1,4 +1,7 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-}
+pub mod chunk;
+pub mod encryption;
+pub mod file;
+pub mod span;
+
+/// The size of a segment, the unit hashed by the binary merkle tree.
+pub const SEGMENT_SIZE: usize = 32;
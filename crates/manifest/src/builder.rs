@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use async_recursion::async_recursion;
+
+use crate::{
+    node::Node, normalize_path, persist::DynLoaderSaver, Entry, MantarayError, Result,
+    NODE_OBFUSCATION_KEY_SIZE,
+};
+
+// ManifestBuilder builds a manifest from paths added in ascending order, saving every subtree
+// that no later path can reach as soon as it is complete. only the nodes along the most
+// recently added path are kept in memory, so large directories can be built in bounded memory.
+pub struct ManifestBuilder {
+    trie: Node,
+    ls: Option<DynLoaderSaver>,
+    last: Option<Vec<u8>>,
+}
+
+impl ManifestBuilder {
+    pub fn new(ls: DynLoaderSaver) -> ManifestBuilder {
+        let trie = Node {
+            obfuscation_key: vec![0; NODE_OBFUSCATION_KEY_SIZE],
+            ..Default::default()
+        };
+
+        ManifestBuilder {
+            trie,
+            ls: Some(ls),
+            last: None,
+        }
+    }
+
+    // add a path and entry to the manifest. paths must be added in strictly ascending order
+    // once normalized.
+    pub async fn add(&mut self, path: &str, entry: Entry) -> Result<()> {
+        let path = normalize_path(path.as_bytes())?;
+        if self.last.as_ref().is_some_and(|last| *last >= path) {
+            return Err(Box::new(MantarayError::PathOutOfOrder(
+                String::from_utf8_lossy(&path).to_string(),
+            )));
+        }
+
+        self.trie
+            .add(&path, &entry.reference, entry.metadata, &mut self.ls)
+            .await?;
+        save_completed(&mut self.trie, &path, &self.ls).await?;
+
+        self.last = Some(path);
+        Ok(())
+    }
+
+    // add a path without metadata.
+    pub async fn add_reference(&mut self, path: &str, reference: &[u8]) -> Result<()> {
+        self.add(
+            path,
+            Entry {
+                reference: reference.to_vec(),
+                metadata: BTreeMap::new(),
+            },
+        )
+        .await
+    }
+
+    // finalize saves the rest of the trie and returns the reference of its root.
+    pub async fn finalize(mut self) -> Result<Vec<u8>> {
+        self.trie.save(&self.ls).await?;
+
+        Ok(self.trie.ref_)
+    }
+}
+
+// save_completed saves every fork of n that is off path. as paths are added in ascending order,
+// every later path sorts after all the paths in those forks, so none of them can change again.
+#[async_recursion]
+async fn save_completed(n: &mut Node, path: &[u8], ls: &Option<DynLoaderSaver>) -> Result<()> {
+    for (key, fork) in n.forks.iter_mut() {
+        if path.first() == Some(key) && path.starts_with(&fork.prefix) {
+            save_completed(&mut fork.node, &path[fork.prefix.len()..], ls).await?;
+        } else {
+            fork.node.save_recursive(ls).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::{persist::MockLoadSaver, Manifest};
+
+    #[tokio::test]
+    async fn builds_manifest_incrementally() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+
+        let mut paths: Vec<String> = (0..1000u32)
+            .map(|i| {
+                let dir = (b'a' + (i % 7) as u8) as char;
+                format!("{dir}/sub{}/file{i}.txt", i % 13)
+            })
+            .collect();
+        paths.sort();
+        let reference = |i: usize| vec![(i % 255) as u8 + 1; 32];
+
+        let mut builder = ManifestBuilder::new(Box::new(ls.clone()));
+        for (i, path) in paths.iter().enumerate() {
+            builder.add_reference(path, &reference(i)).await.unwrap();
+
+            // only the fork along the last path is held in memory, the others are saved
+            for (key, fork) in builder.trie.forks.iter() {
+                if *key != path.as_bytes()[0] {
+                    assert!(!fork.node.ref_.is_empty() && fork.node.forks.is_empty());
+                }
+            }
+        }
+        let root = builder.finalize().await.unwrap();
+
+        let mut m = Manifest::new_manifest_reference(root.clone(), Box::new(ls.clone())).unwrap();
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(m.lookup(path).await.unwrap().reference, reference(i));
+        }
+
+        // the same trie as one built in memory and saved at once
+        let mut m = Manifest::new(Box::new(ls), false);
+        for (i, path) in paths.iter().enumerate() {
+            m.add(
+                path,
+                Entry {
+                    reference: reference(i),
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        assert_eq!(m.store().await.unwrap(), root);
+    }
+
+    #[tokio::test]
+    async fn rejects_paths_out_of_order() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut builder = ManifestBuilder::new(Box::new(ls));
+
+        builder.add_reference("b.txt", &[1; 32]).await.unwrap();
+        assert!(builder.add_reference("a.txt", &[2; 32]).await.is_err());
+        assert!(builder.add_reference("b.txt", &[2; 32]).await.is_err());
+        builder.add_reference("c.txt", &[3; 32]).await.unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    node::{Fork, Node},
+    Manifest, MantarayError, Result, NODE_OBFUSCATION_KEY_SIZE, NT_EDGE, NT_VALUE,
+    NT_WITH_METADATA, PATH_SEPARATOR,
+};
+
+// root metadata keys recognised by bee when serving a manifest as a feed
+pub const FEED_OWNER_KEY: &str = "swarm-feed-owner";
+pub const FEED_TOPIC_KEY: &str = "swarm-feed-topic";
+pub const FEED_TYPE_KEY: &str = "swarm-feed-type";
+
+// the feed reference bee expects at the root of a feed manifest is the zero address
+const FEED_ENTRY_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedType {
+    Sequence,
+    Epoch,
+}
+
+impl FeedType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FeedType::Sequence => "Sequence",
+            FeedType::Epoch => "Epoch",
+        }
+    }
+}
+
+// a feed that a manifest points a name at, by the owner and topic bee looks its updates up by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedDescriptor {
+    pub owner: [u8; 20],
+    pub topic: [u8; 32],
+    pub type_: FeedType,
+}
+
+impl Manifest {
+    // new_feed creates the root node of a manifest pointing at the sequence feed of owner and
+    // topic, as bee does for a feed upload: the root path "/" holds a zero entry whose metadata
+    // names the feed.
+    pub fn new_feed(owner: [u8; 20], topic: [u8; 32]) -> Node {
+        let mut metadata = BTreeMap::new();
+        metadata.insert(FEED_OWNER_KEY.to_string(), hex::encode(owner));
+        metadata.insert(FEED_TOPIC_KEY.to_string(), hex::encode(topic));
+        metadata.insert(
+            FEED_TYPE_KEY.to_string(),
+            FeedType::Sequence.as_str().to_string(),
+        );
+
+        let root_path = Node {
+            node_type: NT_VALUE | NT_WITH_METADATA,
+            ref_bytes_size: FEED_ENTRY_SIZE as u32,
+            obfuscation_key: vec![0; NODE_OBFUSCATION_KEY_SIZE],
+            entry: vec![0; FEED_ENTRY_SIZE],
+            metadata,
+            ..Default::default()
+        };
+
+        let mut root = Node {
+            node_type: NT_EDGE,
+            ref_bytes_size: FEED_ENTRY_SIZE as u32,
+            obfuscation_key: vec![0; NODE_OBFUSCATION_KEY_SIZE],
+            ..Default::default()
+        };
+        root.forks.insert(
+            PATH_SEPARATOR.as_bytes()[0],
+            Fork {
+                prefix: PATH_SEPARATOR.as_bytes().to_vec(),
+                node: root_path,
+            },
+        );
+
+        root
+    }
+
+    // feed returns the feed the manifest points at, or none if it is not a feed manifest.
+    pub async fn feed(&mut self) -> Result<Option<FeedDescriptor>> {
+        let metadata = self.root_metadata().await?;
+
+        let (owner, topic) = match (metadata.get(FEED_OWNER_KEY), metadata.get(FEED_TOPIC_KEY)) {
+            (Some(owner), Some(topic)) => (owner, topic),
+            _ => return Ok(None),
+        };
+
+        // bee assumes a sequence feed when the type is not given
+        let type_ = match metadata.get(FEED_TYPE_KEY).map(String::as_str) {
+            None | Some("Sequence") => FeedType::Sequence,
+            Some("Epoch") => FeedType::Epoch,
+            Some(other) => return Err(invalid_feed(FEED_TYPE_KEY, other)),
+        };
+
+        Ok(Some(FeedDescriptor {
+            owner: decode_hex(FEED_OWNER_KEY, owner)?,
+            topic: decode_hex(FEED_TOPIC_KEY, topic)?,
+            type_,
+        }))
+    }
+}
+
+fn decode_hex<const N: usize>(key: &str, value: &str) -> Result<[u8; N]> {
+    hex::decode(value.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid_feed(key, value))
+}
+
+fn invalid_feed(key: &str, value: &str) -> Box<dyn std::error::Error + Send> {
+    Box::new(MantarayError::InvalidFeed(format!("{key}: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::{marshal::Marshal, persist::MockLoadSaver, Entry};
+
+    const OWNER: [u8; 20] = [0xab; 20];
+    const TOPIC: [u8; 32] = [0xcd; 32];
+
+    #[tokio::test]
+    async fn feed_manifest_round_trip() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+
+        let mut root = Manifest::new_feed(OWNER, TOPIC);
+        root.save(&Some(Box::new(ls.clone()))).await.unwrap();
+
+        let mut m = Manifest::new_manifest_reference(root.ref_.clone(), Box::new(ls)).unwrap();
+        assert_eq!(
+            m.feed().await.unwrap(),
+            Some(FeedDescriptor {
+                owner: OWNER,
+                topic: TOPIC,
+                type_: FeedType::Sequence,
+            })
+        );
+
+        let root_path = m.trie.lookup_node(b"/", &mut m.ls).await.unwrap();
+        assert_eq!(root_path.metadata[FEED_OWNER_KEY], hex::encode(OWNER));
+        assert_eq!(root_path.entry, vec![0; 32]);
+    }
+
+    #[tokio::test]
+    async fn feed_manifest_matches_set_root() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+
+        // the same node as setting the feed metadata on the root of an empty manifest
+        let mut m = Manifest::new(Box::new(ls), false);
+        let mut metadata = BTreeMap::new();
+        metadata.insert(FEED_OWNER_KEY.to_string(), hex::encode(OWNER));
+        metadata.insert(FEED_TOPIC_KEY.to_string(), hex::encode(TOPIC));
+        metadata.insert(FEED_TYPE_KEY.to_string(), "Sequence".to_string());
+        m.set_root(metadata).await.unwrap();
+
+        assert_eq!(
+            Manifest::new_feed(OWNER, TOPIC).marshal_binary().unwrap(),
+            m.trie.marshal_binary().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn not_a_feed_manifest() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(ls), false);
+        m.add(
+            "index.html",
+            Entry {
+                reference: vec![1; 32],
+                metadata: BTreeMap::new(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(m.feed().await.unwrap(), None);
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert(FEED_OWNER_KEY.to_string(), "not hex".to_string());
+        metadata.insert(FEED_TOPIC_KEY.to_string(), hex::encode(TOPIC));
+        m.set_root(metadata).await.unwrap();
+        assert!(m.feed().await.is_err());
+    }
+}
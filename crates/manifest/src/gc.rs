@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::{
+    marshal::Marshal,
+    node::Node,
+    persist::{Address, LoaderError, LoaderSaver, PinStore, Sweeper},
+    Result,
+};
+
+// chunk layout, as written by swarm-split and sent to bee
+const SPAN_SIZE: usize = 8;
+const CHUNK_SIZE: u64 = 4096;
+const BRANCHES: u64 = 128;
+const REFERENCE_SIZE: usize = 32;
+// a single owner chunk starts with its 32 byte id and 65 byte signature
+const SOC_HEADER_SIZE: usize = 32 + 65;
+
+#[derive(Error, Debug, Clone)]
+pub enum GcError {
+    #[error("Encrypted reference {0} can't be walked")]
+    EncryptedReference(String),
+}
+
+// gc deletes everything in store that is not reachable from roots or the roots pinned in the
+// store, returning how many items were deleted. manifest nodes are walked through their forks to the entry of every value
+// node, and the chunk trees of files through their intermediate chunks, whether the root of a
+// tree is a content or a single owner chunk. references that are not in the store are taken to
+// be held elsewhere. encrypted references are refused, as their trees can't be walked without
+// decrypting them, and their chunks would otherwise be deleted.
+pub async fn gc<S: LoaderSaver + Sweeper + PinStore>(
+    roots: &[Address],
+    store: &mut S,
+) -> Result<u64> {
+    let mut pending: Vec<Vec<u8>> = roots.iter().map(|r| r.to_vec()).collect();
+    pending.extend(store.pins().await?);
+    let reachable = reachable(pending, store).await?;
+
+    let mut deleted = 0;
+    for ref_ in store.addresses().await? {
+        if !reachable.contains(&ref_) {
+            store.delete(&ref_).await?;
+            deleted += 1;
+        }
+    }
+
+    Ok(deleted)
+}
+
+// reachable returns roots and every reference reachable from them through manifest nodes and
+// the chunk trees of files, loading each through l at most once. references that l doesn't
+// hold are included but not descended into, and encrypted references are refused.
+pub(crate) async fn reachable<L: LoaderSaver + ?Sized>(
+    roots: Vec<Vec<u8>>,
+    l: &mut L,
+) -> Result<HashSet<Vec<u8>>> {
+    let mut reachable = HashSet::new();
+    let mut pending = roots;
+
+    while let Some(ref_) = pending.pop() {
+        if ref_.len() > REFERENCE_SIZE {
+            return Err(Box::new(GcError::EncryptedReference(hex::encode(&ref_))));
+        }
+
+        if !reachable.insert(ref_.clone()) {
+            continue;
+        }
+
+        let data = match l.load(&ref_).await {
+            Ok(data) => data,
+            Err(e) if matches!(e.downcast_ref(), Some(LoaderError::NotFound(_))) => continue,
+            Err(e) => return Err(e),
+        };
+
+        pending.extend(children(&data));
+    }
+
+    Ok(reachable)
+}
+
+// children returns the references held by data, read as a manifest node, or failing that as a
+// content or single owner chunk.
+fn children(data: &[u8]) -> Vec<Vec<u8>> {
+    // unmarshalling deobfuscates in place, which would garble a chunk
+    let mut n = Node::default();
+    if n.unmarshal_binary(&mut data.to_vec()).is_ok() {
+        let mut children: Vec<Vec<u8>> = n.forks.into_values().map(|f| f.node.ref_).collect();
+
+        // directories and the root carry an empty or zero entry
+        if n.entry.iter().any(|b| *b != 0) {
+            children.push(n.entry);
+        }
+
+        return children;
+    }
+
+    chunk_children(data)
+        .or_else(|| chunk_children(data.get(SOC_HEADER_SIZE..)?))
+        .unwrap_or_default()
+}
+
+// chunk_children returns the references of an intermediate chunk, none for a leaf chunk, or
+// None if data is not a chunk whose payload length is consistent with its span.
+fn chunk_children(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let span = u64::from_le_bytes(data.get(..SPAN_SIZE)?.try_into().unwrap());
+    let payload = &data[SPAN_SIZE..];
+
+    if span <= CHUNK_SIZE {
+        return (payload.len() as u64 == span).then(Vec::new);
+    }
+
+    // the span covered by each reference of the intermediate chunk
+    let mut child_span = CHUNK_SIZE;
+    while child_span.saturating_mul(BRANCHES) < span {
+        child_span *= BRANCHES;
+    }
+
+    let references = span.div_ceil(child_span) as usize * REFERENCE_SIZE;
+    (payload.len() == references)
+        .then(|| payload.chunks(REFERENCE_SIZE).map(|r| r.to_vec()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        persist::{FsLoaderSaver, MockLoadSaver},
+        Entry, Manifest,
+    };
+
+    fn chunk(span: u64, payload: &[u8]) -> Vec<u8> {
+        [&span.to_le_bytes()[..], payload].concat()
+    }
+
+    #[tokio::test]
+    async fn collects_unreachable_chunks() {
+        let root = std::env::temp_dir().join(format!("manifest-gc-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        let mut ls = FsLoaderSaver::new(&root);
+        let write = |address: Address, data: Vec<u8>| {
+            std::fs::write(root.join(hex::encode(address)), data).unwrap();
+            address
+        };
+
+        // a small file shared by both manifests
+        let shared = write([0x11; 32], chunk(5, b"hello"));
+
+        // a file of two full leaves under a content chunk
+        let a1 = write([0xa1; 32], chunk(CHUNK_SIZE, &[1; 4096]));
+        let a2 = write([0xa2; 32], chunk(CHUNK_SIZE, &[2; 4096]));
+        let a = write([0xa0; 32], chunk(2 * CHUNK_SIZE, &[a1, a2].concat()));
+
+        // a file under a single owner chunk wrapping an intermediate chunk
+        let b1 = write([0xb1; 32], chunk(CHUNK_SIZE, &[3; 4096]));
+        let b2 = write([0xb2; 32], chunk(904, &[4; 904]));
+        let b = write(
+            [0xb0; 32],
+            [&[0x22; 32][..], &[0; 65], &chunk(5000, &[b1, b2].concat())].concat(),
+        );
+
+        let mut manifests = Vec::new();
+        for files in [
+            [("a.bin", a), ("shared.txt", shared)],
+            [("b.bin", b), ("shared.txt", shared)],
+        ] {
+            let mut m = Manifest::new(Box::new(ls.clone()), false);
+            for (path, reference) in files {
+                m.add(
+                    path,
+                    Entry {
+                        reference: reference.to_vec(),
+                        metadata: BTreeMap::new(),
+                    },
+                )
+                .await
+                .unwrap();
+            }
+            manifests.push(m.store().await.unwrap());
+        }
+
+        let mut keep = Node::new_node_ref(&manifests[0]);
+        let mut l: Option<crate::persist::DynLoaderSaver> = Some(Box::new(ls.clone()));
+        let kept = keep.reachable_addresses(&mut l).await.unwrap();
+        for address in [a, a1, a2, shared] {
+            assert!(kept.contains(&address.to_vec()));
+        }
+        let before = ls.addresses().await.unwrap().len();
+
+        let root_a: Address = manifests[0].clone().try_into().unwrap();
+        let deleted = gc(&[root_a], &mut ls).await.unwrap();
+
+        // everything reachable from the first manifest is kept, down to the leaves
+        for address in &kept {
+            assert!(ls.load(address).await.is_ok(), "{}", hex::encode(address));
+        }
+
+        // the second manifest and its file tree are gone
+        for address in [manifests[1].clone(), b.to_vec(), b1.to_vec(), b2.to_vec()] {
+            assert!(ls.load(&address).await.is_err());
+        }
+        assert_eq!(deleted as usize, before - kept.len());
+        assert_eq!(ls.addresses().await.unwrap().len(), kept.len());
+
+        // collecting again deletes nothing more
+        assert_eq!(gc(&[root_a], &mut ls).await.unwrap(), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_pinned_roots() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut store = ls.clone();
+
+        let mut manifests = Vec::new();
+        for path in ["a.txt", "b.txt"] {
+            let mut m = Manifest::new(Box::new(ls.clone()), false);
+            m.add(
+                path,
+                Entry {
+                    reference: store.save(path.as_bytes()).await.unwrap(),
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+            manifests.push(m.store().await.unwrap());
+        }
+        let unrelated: Address = store.save(b"unrelated").await.unwrap().try_into().unwrap();
+
+        // the pinned manifest survives collection with an unrelated root
+        store.pin(&manifests[0]).await.unwrap();
+        let deleted = gc(&[unrelated], &mut store).await.unwrap();
+        assert!(deleted > 0);
+
+        let mut m =
+            Manifest::new_manifest_reference(manifests[0].clone(), Box::new(ls.clone())).unwrap();
+        let entry = m.lookup("a.txt").await.unwrap();
+        assert_eq!(store.load(&entry.reference).await.unwrap(), b"a.txt");
+        assert!(store.load(&manifests[1]).await.is_err());
+        assert!(store.load(&unrelated).await.is_ok());
+
+        // once unpinned it is collected like the rest
+        assert!(store.unpin(&manifests[0]).await.unwrap());
+        assert!(!store.unpin(&manifests[0]).await.unwrap());
+        gc(&[unrelated], &mut store).await.unwrap();
+        assert!(store.load(&manifests[0]).await.is_err());
+        assert!(store.load(&entry.reference).await.is_err());
+        assert_eq!(store.addresses().await.unwrap(), vec![unrelated.to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn refuses_encrypted_references() {
+        let root = std::env::temp_dir().join(format!("manifest-gc-enc-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        // a node holding the entry of an encrypted file, its address followed by its key
+        let mut ls = FsLoaderSaver::new(&root);
+        let n = Node {
+            node_type: crate::NT_VALUE,
+            ref_bytes_size: 64,
+            obfuscation_key: vec![0; 32],
+            entry: vec![7; 64],
+            ..Default::default()
+        };
+        let reference: Address = ls
+            .save(&n.marshal_binary().unwrap())
+            .await
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        let err = gc(&[reference], &mut ls).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(GcError::EncryptedReference(_))
+        ));
+        assert!(ls.load(&reference).await.is_ok());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
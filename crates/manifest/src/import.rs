@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+
+use file::{chunk::Options, file::ChunkedFile};
+use nectar_primitives::chunk::{Chunk, ContentChunk};
+use nectar_primitives_traits::ChunkStore;
+
+use crate::{persist::DynLoaderSaver, Entry, Manifest, Result};
+
+// metadata keys recognised by bee when serving a file from a manifest
+pub const CONTENT_TYPE_KEY: &str = "Content-Type";
+pub const FILENAME_KEY: &str = "Filename";
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+impl Manifest {
+    // from_tar chunks every regular file of a tar archive into store and stores a manifest
+    // through ls mapping the file paths to the addresses of their root chunks, returning the
+    // manifest reference.
+    pub async fn from_tar<S>(reader: impl Read, ls: DynLoaderSaver, store: &S) -> Result<Vec<u8>>
+    where
+        S: ChunkStore<Chunk = Chunk>,
+    {
+        // read the archive up front so that it isn't held across awaits
+        let mut files = Vec::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(box_err)? {
+            let mut entry = entry.map_err(box_err)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path().map_err(box_err)?;
+            let path = path.to_string_lossy().trim_start_matches("./").to_string();
+
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).map_err(box_err)?;
+            files.push((path, data));
+        }
+
+        let mut m = Manifest::new(ls, false);
+        for (path, data) in files {
+            let reference = store_file(&data, store).await?;
+
+            let mut metadata = BTreeMap::new();
+            let mut mime = content_type(&path);
+            if mime == DEFAULT_CONTENT_TYPE {
+                mime = sniff_content_type(&data);
+            }
+            metadata.insert(CONTENT_TYPE_KEY.to_string(), mime.to_string());
+            if let Some(name) = Path::new(&path).file_name() {
+                metadata.insert(FILENAME_KEY.to_string(), name.to_string_lossy().to_string());
+            }
+
+            m.add(
+                &path,
+                Entry {
+                    reference,
+                    metadata,
+                },
+            )
+            .await?;
+        }
+
+        m.store().await
+    }
+}
+
+// store_file splits data into the chunks of its swarm file tree and puts them in store, returning
+// the address of the root chunk.
+async fn store_file<S>(data: &[u8], store: &S) -> Result<Vec<u8>>
+where
+    S: ChunkStore<Chunk = Chunk>,
+{
+    let file = ChunkedFile::new(data.to_vec(), Options::default());
+    for chunk in file.all_chunks() {
+        let payload = chunk.data()[..chunk.payload_length].to_vec();
+        let chunk = ContentChunk::new_with_span(chunk.span().value(), payload).map_err(box_err)?;
+        store.put(Chunk::Content(chunk)).await.map_err(box_err)?;
+    }
+
+    Ok(file.address().to_vec())
+}
+
+// content_type guesses the mime type of a file from its extension.
+pub fn content_type(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("xml") => "text/xml; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        _ => DEFAULT_CONTENT_TYPE,
+    }
+}
+
+// sniff_content_type guesses the mime type of data from its leading magic number, or as text if
+// it is valid utf-8 without control characters, for files whose extension says nothing.
+pub fn sniff_content_type(data: &[u8]) -> &'static str {
+    const MAGIC: [(&[u8], &str); 5] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+    ];
+
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return mime;
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(text) if !text.chars().any(|c| c.is_control() && !c.is_whitespace()) => {
+            "text/plain; charset=utf-8"
+        }
+        _ => DEFAULT_CONTENT_TYPE,
+    }
+}
+
+fn box_err(e: impl Error + Send + 'static) -> Box<dyn Error + Send> {
+    Box::new(e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persist::MockLoadSaver;
+
+    use async_recursion::async_recursion;
+    use nectar_primitives::store::MemoryChunkStore;
+    use nectar_primitives_traits::{ChunkAddress, ChunkData, CHUNK_SIZE};
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    // join reassembles the file rooted at address from the chunks in store
+    #[async_recursion]
+    async fn join(store: &MemoryChunkStore, address: &[u8]) -> Vec<u8> {
+        let chunk = match store.get(ChunkAddress::from_slice(address)).await.unwrap() {
+            Some(Chunk::Content(chunk)) => chunk,
+            other => panic!("expected a content chunk, got {other:?}"),
+        };
+
+        let data = chunk.data();
+        if chunk.span() <= CHUNK_SIZE as u64 {
+            return data.to_vec();
+        }
+
+        let mut joined = Vec::new();
+        for child in data.chunks(32) {
+            joined.extend(join(store, child).await);
+        }
+        joined
+    }
+
+    fn tar_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *data).unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    #[tokio::test]
+    async fn from_tar() {
+        // spans several chunks, so the entry must be the root of a file tree
+        let large: Vec<u8> = (0..3 * CHUNK_SIZE + 100).map(|i| (i % 251) as u8).collect();
+        let files: [(&str, &[u8]); 3] = [
+            ("index.html", b"<h1>hello</h1>"),
+            ("img/logo.png", b"not really a png"),
+            ("video.mp4", &large),
+        ];
+
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let store = MemoryChunkStore::new();
+        let reference = Manifest::from_tar(tar_of(&files).as_slice(), Box::new(ls.clone()), &store)
+            .await
+            .unwrap();
+        assert_eq!(store.len(), 2 + 4 + 1);
+
+        let mut m = Manifest::new_manifest_reference(reference, Box::new(ls)).unwrap();
+        for (path, data) in files {
+            let entry = m.lookup(path).await.unwrap();
+            assert_eq!(
+                entry.reference,
+                ChunkedFile::new(data.to_vec(), Options::default()).address()
+            );
+            assert_eq!(join(&store, &entry.reference).await, data);
+            assert_eq!(entry.metadata[CONTENT_TYPE_KEY], content_type(path));
+        }
+
+        let entry = m.lookup("img/logo.png").await.unwrap();
+        assert_eq!(entry.metadata[CONTENT_TYPE_KEY], "image/png");
+        assert_eq!(entry.metadata[FILENAME_KEY], "logo.png");
+    }
+
+    #[test]
+    fn guesses_content_type() {
+        assert_eq!(content_type("a/b/index.HTML"), "text/html; charset=utf-8");
+        assert_eq!(content_type("script.js"), "text/javascript; charset=utf-8");
+        assert_eq!(content_type("README"), DEFAULT_CONTENT_TYPE);
+        assert_eq!(content_type("archive.tar.gz"), DEFAULT_CONTENT_TYPE);
+    }
+
+    #[test]
+    fn sniffs_content_type() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        assert_eq!(sniff_content_type(png), "image/png");
+        assert_eq!(sniff_content_type(b"%PDF-1.7\n"), "application/pdf");
+        assert_eq!(
+            sniff_content_type("héllo wörld\n\tindented".as_bytes()),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(
+            sniff_content_type(&[0, 1, 2, 0xfe, 0xff]),
+            DEFAULT_CONTENT_TYPE
+        );
+        assert_eq!(sniff_content_type(b"nul\0in text"), DEFAULT_CONTENT_TYPE);
+    }
+
+    #[tokio::test]
+    async fn from_tar_sniffs_unknown_extensions() {
+        let files: [(&str, &[u8]); 3] = [
+            ("LICENSE", b"MIT License"),
+            ("photo", b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            ("blob.bin", &[0, 159, 146, 150]),
+        ];
+
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let store = MemoryChunkStore::new();
+        let reference = Manifest::from_tar(tar_of(&files).as_slice(), Box::new(ls.clone()), &store)
+            .await
+            .unwrap();
+
+        let mut m = Manifest::new_manifest_reference(reference, Box::new(ls)).unwrap();
+        for (path, mime) in [
+            ("LICENSE", "text/plain; charset=utf-8"),
+            ("photo", "image/jpeg"),
+            ("blob.bin", DEFAULT_CONTENT_TYPE),
+        ] {
+            assert_eq!(
+                m.lookup(path).await.unwrap().metadata[CONTENT_TYPE_KEY],
+                mime
+            );
+        }
+    }
+}
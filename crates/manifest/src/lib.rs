@@ -1,11 +1,15 @@
-#![feature(let_chains)]
 use std::collections::BTreeMap;
 
 use node::Node;
 use persist::DynLoaderSaver;
+use rand::RngCore;
 use thiserror::Error;
 use tiny_keccak::{Hasher, Keccak};
 
+pub mod builder;
+pub mod feed;
+pub mod gc;
+pub mod import;
 pub mod marshal;
 pub mod node;
 pub mod persist;
@@ -14,6 +18,10 @@ pub mod walker;
 
 const PATH_SEPARATOR: &str = "/";
 
+// root metadata keys recognised by bee when serving a manifest as a website
+pub const WEBSITE_INDEX_DOCUMENT_SUFFIX_KEY: &str = "website-index-document";
+pub const WEBSITE_ERROR_DOCUMENT_PATH_KEY: &str = "website-error-document";
+
 // node header field constraints
 const NODE_OBFUSCATION_KEY_SIZE: usize = 32;
 const VERSION_HASH_SIZE: usize = 31;
@@ -42,6 +50,14 @@ pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send>>;
 pub enum MantarayError {
     #[error("Not a value type")]
     NotValueType,
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Path added out of order: {0}")]
+    PathOutOfOrder(String),
+    #[error("Path traverses out of the manifest: {0}")]
+    PathTraversal(String),
+    #[error("Invalid feed metadata: {0}")]
+    InvalidFeed(String),
 }
 
 pub struct Manifest {
@@ -52,17 +68,27 @@ pub struct Manifest {
 impl Manifest {
     // new manataray manifest creates a new mantaray-based manifest.
     pub fn new(ls: DynLoaderSaver, encrypted: bool) -> Manifest {
-        let mut mm = Manifest {
-            ls: Some(ls),
-            trie: Node::default(),
-        };
-
-        // use emtpy obfuscation key if encryption is not enabled
-        if !encrypted {
-            mm.trie.obfuscation_key = [0u8; NODE_OBFUSCATION_KEY_SIZE].to_vec();
+        // use a random obfuscation key if encryption is enabled, otherwise an empty one
+        let mut obfuscation_key = [0u8; NODE_OBFUSCATION_KEY_SIZE];
+        if encrypted {
+            rand::thread_rng().fill_bytes(&mut obfuscation_key);
         }
 
-        mm
+        Self::new_with_obfuscation_key(ls, obfuscation_key)
+    }
+
+    // new_with_obfuscation_key creates a new mantaray-based manifest whose nodes are all
+    // obfuscated with the given key, eg. for deterministic output.
+    pub fn new_with_obfuscation_key(
+        ls: DynLoaderSaver,
+        obfuscation_key: [u8; NODE_OBFUSCATION_KEY_SIZE],
+    ) -> Manifest {
+        let trie = Node {
+            obfuscation_key: obfuscation_key.to_vec(),
+            ..Default::default()
+        };
+
+        Manifest { ls: Some(ls), trie }
     }
 
     // new_manifest_reference loads existing mantaray-based manifest.
@@ -79,7 +105,7 @@ impl Manifest {
     pub async fn add(&mut self, path: &str, entry: Entry) -> Result<()> {
         self.trie
             .add(
-                path.as_bytes(),
+                &normalize_path(path.as_bytes())?,
                 &entry.reference,
                 entry.metadata,
                 &mut self.ls,
@@ -87,14 +113,19 @@ impl Manifest {
             .await
     }
 
-    // remove a path from the manifest.
-    pub async fn remove(&mut self, path: &str) -> Result<()> {
-        self.trie.remove(path.as_bytes(), &mut self.ls).await
+    // remove a path from the manifest, returning whether it was present.
+    pub async fn remove(&mut self, path: &str) -> Result<bool> {
+        self.trie
+            .remove(&normalize_path(path.as_bytes())?, &mut self.ls)
+            .await
     }
 
     // lookup a path in the manifest.
     pub async fn lookup(&mut self, path: &str) -> Result<Entry> {
-        let n = self.trie.lookup_node(path.as_bytes(), &mut self.ls).await?;
+        let n = self
+            .trie
+            .lookup_node(&normalize_path(path.as_bytes())?, &mut self.ls)
+            .await?;
 
         // if the node is not a value type, return not found.
         if !n.is_value_type() {
@@ -112,17 +143,14 @@ impl Manifest {
 
     // determine if the manifest has a specified prefix.
     pub async fn has_prefix(&mut self, prefix: &str) -> Result<bool> {
-        self.trie.has_prefix(prefix.as_bytes(), &mut self.ls).await
+        self.trie
+            .has_prefix(&normalize_path(prefix.as_bytes())?, &mut self.ls)
+            .await
     }
 
     pub async fn set_root(&mut self, metadata: BTreeMap<String, String>) -> Result<()> {
         self.trie
-            .add(
-                "/".as_bytes(),
-                &vec![0; 32].to_vec(),
-                metadata,
-                &mut self.ls,
-            )
+            .add("/".as_bytes(), [0; 32].as_ref(), metadata, &mut self.ls)
             .await?;
         let root_node = self.trie.lookup_node("/".as_bytes(), &mut self.ls).await?;
         let mut type_ = root_node.node_type;
@@ -132,6 +160,105 @@ impl Manifest {
         Ok(())
     }
 
+    // set_index_document sets the document served for the root and for directory paths of a
+    // website, relative to the directory, eg. "index.html".
+    pub async fn set_index_document(&mut self, path: &str) -> Result<()> {
+        self.set_root_metadata(WEBSITE_INDEX_DOCUMENT_SUFFIX_KEY, path)
+            .await
+    }
+
+    // set_error_document sets the document served for paths of a website that don't exist.
+    pub async fn set_error_document(&mut self, path: &str) -> Result<()> {
+        self.set_root_metadata(WEBSITE_ERROR_DOCUMENT_PATH_KEY, path)
+            .await
+    }
+
+    // resolve looks up a path as a website would serve it. the root and directory paths
+    // resolve to their index document, and paths that don't exist to the error document.
+    pub async fn resolve(&mut self, path: &str) -> Result<Entry> {
+        let path = path.trim_start_matches(PATH_SEPARATOR);
+        if !path.is_empty() && !path.ends_with(PATH_SEPARATOR) {
+            if let Ok(entry) = self.lookup(path).await {
+                return Ok(entry);
+            }
+        }
+
+        let metadata = self.root_metadata().await?;
+
+        if let Some(index) = metadata.get(WEBSITE_INDEX_DOCUMENT_SUFFIX_KEY) {
+            let dir = match path.is_empty() || path.ends_with(PATH_SEPARATOR) {
+                true => path.to_string(),
+                false => format!("{path}{PATH_SEPARATOR}"),
+            };
+
+            if let Ok(entry) = self.lookup(&format!("{dir}{index}")).await {
+                return Ok(entry);
+            }
+        }
+
+        if let Some(error) = metadata.get(WEBSITE_ERROR_DOCUMENT_PATH_KEY) {
+            return self.lookup(error).await;
+        }
+
+        Err(Box::new(MantarayError::NotFound(path.to_string())))
+    }
+
+    // the metadata of the root path, empty if it has not been set.
+    async fn root_metadata(&mut self) -> Result<BTreeMap<String, String>> {
+        match self
+            .trie
+            .lookup_node(PATH_SEPARATOR.as_bytes(), &mut self.ls)
+            .await
+        {
+            Ok(n) if n.is_value_type() => Ok(n.metadata.clone()),
+            _ => Ok(BTreeMap::new()),
+        }
+    }
+
+    async fn set_root_metadata(&mut self, key: &str, value: &str) -> Result<()> {
+        let mut metadata = self.root_metadata().await?;
+        metadata.insert(key.to_string(), value.to_string());
+
+        self.set_root(metadata).await
+    }
+
+    // diff compares the entries of two tries by path, classifying each path as added, removed
+    // or modified when its entry reference changed, eg. to re-upload only what changed between
+    // two deploys of a site. metadata is not compared.
+    pub async fn diff(
+        old: &Node,
+        new: &Node,
+        l: &mut Option<DynLoaderSaver>,
+    ) -> Result<ManifestDiff> {
+        let old = old.clone().entries(l).await?;
+        let new = new.clone().entries(l).await?;
+
+        let mut diff = ManifestDiff::default();
+        let (mut old, mut new) = (old.into_iter().peekable(), new.into_iter().peekable());
+
+        // both are sorted by path, so walk them side by side
+        loop {
+            match (old.peek(), new.peek()) {
+                (None, None) => break,
+                (Some(_), None) => diff.removed.push(old.next().unwrap().0),
+                (None, Some(_)) => diff.added.push(new.next().unwrap().0),
+                (Some((o, _)), Some((n, _))) => match o.cmp(n) {
+                    std::cmp::Ordering::Less => diff.removed.push(old.next().unwrap().0),
+                    std::cmp::Ordering::Greater => diff.added.push(new.next().unwrap().0),
+                    std::cmp::Ordering::Equal => {
+                        let (path, o) = old.next().unwrap();
+                        let (_, n) = new.next().unwrap();
+                        if o.reference != n.reference {
+                            diff.modified.push(path);
+                        }
+                    }
+                },
+            }
+        }
+
+        Ok(diff)
+    }
+
     pub async fn store(&mut self) -> Result<Vec<u8>> {
         self.trie.save(&Box::new(&self.ls)).await?;
 
@@ -141,6 +268,14 @@ impl Manifest {
     // todo!{"Finish manifest implementation"}
 }
 
+// the paths whose entries differ between two manifests, each sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ManifestDiff {
+    pub added: Vec<Vec<u8>>,
+    pub removed: Vec<Vec<u8>>,
+    pub modified: Vec<Vec<u8>>,
+}
+
 // define a trait that represents a single manifest entry.
 pub struct Entry {
     pub reference: Reference,
@@ -159,3 +294,241 @@ where
     hasher.finalize(&mut output);
     output
 }
+
+// normalize_path turns a path into the key it is stored under in the trie, so that paths
+// naming the same file, eg. "./foo//bar" and "/foo/bar", are added and looked up alike.
+// duplicate separators are collapsed, "." segments dropped and a leading separator stripped,
+// while a trailing separator is kept for directory paths. ".." segments are rejected rather
+// than resolved, as they could otherwise be used to reach paths outside of an upload's
+// directory. a path of only separators and "." segments is the root path "/", which holds the
+// root metadata.
+pub fn normalize_path(path: &[u8]) -> Result<Vec<u8>> {
+    let sep = PATH_SEPARATOR.as_bytes()[0];
+
+    let mut normalized = Vec::with_capacity(path.len());
+    for segment in path.split(|b| *b == sep) {
+        match segment {
+            b"" | b"." => continue,
+            b".." => {
+                return Err(Box::new(MantarayError::PathTraversal(
+                    String::from_utf8_lossy(path).to_string(),
+                )))
+            }
+            _ => {
+                if !normalized.is_empty() {
+                    normalized.push(sep);
+                }
+                normalized.extend_from_slice(segment);
+            }
+        }
+    }
+
+    if path.ends_with(&[sep]) || (normalized.is_empty() && path.contains(&sep)) {
+        normalized.push(sep);
+    }
+
+    Ok(normalized)
+}
+
+// longest_common_prefix returns the length of the longest prefix shared by a and b, which is
+// where a fork prefix and a path diverge when splitting, looking up or walking forks.
+pub(crate) fn longest_common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::persist::MockLoadSaver;
+
+    #[test]
+    fn longest_common_prefix_lengths() {
+        // no overlap
+        assert_eq!(longest_common_prefix(b"abc", b"xyz"), 0);
+        assert_eq!(longest_common_prefix(b"", b"abc"), 0);
+        assert_eq!(longest_common_prefix(b"", b""), 0);
+
+        // partial overlap
+        assert_eq!(longest_common_prefix(b"index.html", b"img/logo.png"), 1);
+        assert_eq!(longest_common_prefix(b"aaaaaa", b"aaaaab"), 5);
+
+        // one is a prefix of the other, or both match fully
+        assert_eq!(longest_common_prefix(b"app.js", b"app.js.map"), 6);
+        assert_eq!(longest_common_prefix(b"app.js.map", b"app.js"), 6);
+        assert_eq!(longest_common_prefix(b"app.js", b"app.js"), 6);
+    }
+
+    #[test]
+    fn normalize_paths() {
+        let cases: [(&str, &str); 10] = [
+            ("foo/bar", "foo/bar"),
+            ("./foo//bar", "foo/bar"),
+            ("/foo/bar", "foo/bar"),
+            ("foo/./bar/.", "foo/bar"),
+            ("foo///bar//", "foo/bar/"),
+            ("docs/", "docs/"),
+            ("/", "/"),
+            ("//./", "/"),
+            ("", ""),
+            ("...hidden/..x", "...hidden/..x"),
+        ];
+        for (path, normalized) in cases {
+            assert_eq!(
+                normalize_path(path.as_bytes()).unwrap(),
+                normalized.as_bytes(),
+                "{path}"
+            );
+        }
+
+        for path in ["../etc/passwd", "foo/../../bar", "foo/.."] {
+            assert!(normalize_path(path.as_bytes()).is_err(), "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn normalized_add_and_lookup() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(ls), false);
+
+        m.add(
+            "./foo//bar.txt",
+            Entry {
+                reference: vec![1; 32],
+                metadata: BTreeMap::new(),
+            },
+        )
+        .await
+        .unwrap();
+
+        for path in ["foo/bar.txt", "/foo/bar.txt", "foo/./bar.txt"] {
+            assert_eq!(m.lookup(path).await.unwrap().reference, vec![1; 32]);
+        }
+        assert!(m.has_prefix("/foo/").await.unwrap());
+
+        let traversal = m
+            .add(
+                "foo/../../bar.txt",
+                Entry {
+                    reference: vec![2; 32],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await;
+        assert!(traversal.is_err());
+
+        assert!(m.remove("//foo/bar.txt").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn resolve_website_documents() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(ls.clone()), false);
+
+        let paths = ["index.html", "404.html", "docs/index.html", "docs/a.md"];
+        for (i, path) in paths.iter().enumerate() {
+            m.add(
+                path,
+                Entry {
+                    reference: vec![i as u8 + 1; 32],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        // without an index document, the root has nothing to serve
+        assert!(m.resolve("/").await.is_err());
+
+        m.set_index_document("index.html").await.unwrap();
+        m.set_error_document("404.html").await.unwrap();
+        let reference = m.store().await.unwrap();
+
+        let mut m = Manifest::new_manifest_reference(reference, Box::new(ls)).unwrap();
+        let resolved = [
+            ("/", 1),
+            ("", 1),
+            ("docs/", 3),
+            ("docs", 3),
+            ("/docs/a.md", 4),
+            ("missing.html", 2),
+            ("img/", 2),
+        ];
+        for (path, reference) in resolved {
+            assert_eq!(
+                m.resolve(path).await.unwrap().reference,
+                vec![reference; 32],
+                "{path}"
+            );
+        }
+
+        let root = m.trie.lookup_node(b"/", &mut m.ls).await.unwrap();
+        assert_eq!(
+            root.metadata[WEBSITE_INDEX_DOCUMENT_SUFFIX_KEY],
+            "index.html"
+        );
+        assert_eq!(root.metadata[WEBSITE_ERROR_DOCUMENT_PATH_KEY], "404.html");
+    }
+
+    #[tokio::test]
+    async fn diff_manifests() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+
+        let mut old = Manifest::new(Box::new(ls.clone()), false);
+        let mut new = Manifest::new(Box::new(ls.clone()), false);
+        for (path, reference) in [("index.html", 1), ("style.css", 2), ("img/logo.png", 3)] {
+            let entry = || Entry {
+                reference: vec![reference; 32],
+                metadata: BTreeMap::new(),
+            };
+            old.add(path, entry()).await.unwrap();
+            new.add(path, entry()).await.unwrap();
+        }
+
+        // one changed and one added file
+        new.add(
+            "style.css",
+            Entry {
+                reference: vec![4; 32],
+                metadata: BTreeMap::new(),
+            },
+        )
+        .await
+        .unwrap();
+        new.add(
+            "img/banner.png",
+            Entry {
+                reference: vec![5; 32],
+                metadata: BTreeMap::new(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let old = Node::new_node_ref(&old.store().await.unwrap());
+        let new = Node::new_node_ref(&new.store().await.unwrap());
+
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(ls));
+        let diff = Manifest::diff(&old, &new, &mut l).await.unwrap();
+        assert_eq!(
+            diff,
+            ManifestDiff {
+                added: vec![b"img/banner.png".to_vec()],
+                removed: vec![],
+                modified: vec![b"style.css".to_vec()],
+            }
+        );
+
+        let diff = Manifest::diff(&new, &old, &mut l).await.unwrap();
+        assert_eq!(diff.removed, vec![b"img/banner.png".to_vec()]);
+        assert!(diff.added.is_empty());
+
+        assert_eq!(
+            Manifest::diff(&old, &old, &mut l).await.unwrap(),
+            ManifestDiff::default()
+        );
+    }
+}
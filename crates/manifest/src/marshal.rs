@@ -160,6 +160,12 @@ impl Marshal for Node {
             // get the ref_bytes_size from the data vector
             let ref_bytes_size = data[NODE_HEADER_SIZE - 1];
 
+            // keep the reference size so the node marshals its forks again after a change. an
+            // empty node is saved with a size of 0, which must not override one already known
+            if ref_bytes_size != 0 {
+                self.ref_bytes_size = ref_bytes_size as u32;
+            }
+
             // get the node entry from the data vector and copy it to the node
             self.entry =
                 data[NODE_HEADER_SIZE..NODE_HEADER_SIZE + ref_bytes_size as usize].to_vec();
@@ -204,6 +210,12 @@ impl Marshal for Node {
             // get the ref_bytes_size from the data vector
             let ref_bytes_size = data[NODE_HEADER_SIZE - 1];
 
+            // keep the reference size so the node marshals its forks again after a change. an
+            // empty node is saved with a size of 0, which must not override one already known
+            if ref_bytes_size != 0 {
+                self.ref_bytes_size = ref_bytes_size as u32;
+            }
+
             // get the node entry from the data vector and copy it to the node
             self.entry =
                 data[NODE_HEADER_SIZE..NODE_HEADER_SIZE + ref_bytes_size as usize].to_vec();
@@ -574,7 +586,7 @@ mod tests {
         let mut marshalled_data = hex::decode(TEST_MARSHAL_OUTPUT_01).unwrap();
         let mut n = Node::default();
 
-        assert_eq!(n.unmarshal_binary(&mut marshalled_data).is_ok(), true);
+        assert!(n.unmarshal_binary(&mut marshalled_data).is_ok());
 
         let expect_encrypted_bytes = hex::decode(&TEST_MARSHAL_OUTPUT_01[128..192]).unwrap();
         let expect_bytes = encrypt_decrypt(&expect_encrypted_bytes, &n.obfuscation_key);
@@ -583,7 +595,7 @@ mod tests {
         assert_eq!(test_entries().len(), n.forks.len());
 
         for entry in test_entries() {
-            assert_eq!(n.forks.contains_key(&entry.path.as_bytes()[0]), true);
+            assert!(n.forks.contains_key(&entry.path.as_bytes()[0]));
             assert_eq!(
                 n.forks[&entry.path.as_bytes()[0]].prefix,
                 entry.path.as_bytes()
@@ -596,7 +608,7 @@ mod tests {
         let mut marshalled_data = hex::decode(TEST_MARSHAL_OUTPUT_02).unwrap();
         let mut n = Node::default();
 
-        assert_eq!(n.unmarshal_binary(&mut marshalled_data).is_ok(), true);
+        assert!(n.unmarshal_binary(&mut marshalled_data).is_ok());
 
         let expect_encrypted_bytes = hex::decode(&TEST_MARSHAL_OUTPUT_02[128..192]).unwrap();
         let expect_bytes = encrypt_decrypt(&expect_encrypted_bytes, &n.obfuscation_key);
@@ -605,13 +617,13 @@ mod tests {
         assert_eq!(test_entries().len(), n.forks.len());
 
         for entry in test_entries() {
-            assert_eq!(n.forks.contains_key(&entry.path.as_bytes()[0]), true);
+            assert!(n.forks.contains_key(&entry.path.as_bytes()[0]));
             assert_eq!(
                 n.forks[&entry.path.as_bytes()[0]].prefix,
                 entry.path.as_bytes()
             );
 
-            if entry.metadata.len() > 0 {
+            if !entry.metadata.is_empty() {
                 assert_eq!(
                     n.forks[&entry.path.as_bytes()[0]].node.metadata,
                     entry.metadata
@@ -619,4 +631,77 @@ mod tests {
             }
         }
     }
+
+    // build the node marshalled in TEST_MARSHAL_OUTPUT_02
+    async fn test_node_02() -> Node {
+        let expected = hex::decode(TEST_MARSHAL_OUTPUT_02).unwrap();
+
+        // the obfuscation key is the first segment of the expected output
+        let mut n = Node {
+            obfuscation_key: expected[..NODE_OBFUSCATION_KEY_SIZE].to_vec(),
+            ..Default::default()
+        };
+
+        for (i, entry) in test_entries().iter().enumerate() {
+            let path = entry.path.as_bytes();
+            let mut reference = vec![0; 32 - path.len()];
+            reference.extend_from_slice(path);
+
+            n.add(path, &reference, entry.metadata.clone(), &mut None)
+                .await
+                .unwrap();
+
+            // stand in for the reference each fork's node would be saved under
+            let mut fork_ref = vec![0; 32];
+            fork_ref[31] = i as u8;
+            n.forks.get_mut(&path[0]).unwrap().node.ref_ = fork_ref;
+        }
+
+        n
+    }
+
+    #[tokio::test]
+    async fn marshal_02() {
+        let n = test_node_02().await;
+        assert_eq!(
+            hex::encode(n.marshal_binary().unwrap()),
+            TEST_MARSHAL_OUTPUT_02
+        );
+    }
+
+    #[tokio::test]
+    async fn marshal_unmarshal_02() {
+        let n = test_node_02().await;
+        let mut marshalled = n.marshal_binary().unwrap();
+
+        let mut unmarshalled = Node::default();
+        unmarshalled.unmarshal_binary(&mut marshalled).unwrap();
+
+        assert_eq!(unmarshalled.obfuscation_key, n.obfuscation_key);
+        assert_eq!(unmarshalled.ref_bytes_size, n.ref_bytes_size);
+        assert!(unmarshalled.is_edge_type());
+        assert_eq!(unmarshalled.forks.len(), n.forks.len());
+
+        // forks come back as references to their nodes, with the type and metadata that
+        // the parent holds for them
+        for (key, fork) in &n.forks {
+            let got = &unmarshalled.forks[key];
+            assert_eq!(got.prefix, fork.prefix);
+            assert_eq!(got.node.ref_, fork.node.ref_);
+            assert_eq!(got.node.node_type, fork.node.node_type);
+            assert_eq!(got.node.metadata, fork.node.metadata);
+        }
+
+        // the metadata is obfuscated in the marshalled bytes
+        let metadata = serde_json::to_vec(&test_entries()[0].metadata).unwrap();
+        assert!(!hex::decode(TEST_MARSHAL_OUTPUT_02)
+            .unwrap()
+            .windows(metadata.len())
+            .any(|w| w == metadata.as_slice()));
+
+        assert_eq!(
+            unmarshalled.marshal_binary().unwrap(),
+            n.marshal_binary().unwrap()
+        );
+    }
 }
@@ -7,7 +7,7 @@ use serde::*;
 use serde_with::serde_as;
 use thiserror::Error;
 
-use crate::Result;
+use crate::{longest_common_prefix, Entry, Result};
 
 use crate::{
     NODE_OBFUSCATION_KEY_SIZE, NODE_PREFIX_MAX_SIZE, NT_EDGE, NT_MASK, NT_VALUE, NT_WITH_METADATA,
@@ -53,30 +53,6 @@ pub enum MantarayNodeError {
     PathPrefixNotFound(String),
 }
 
-// find the index at which a subslice exists within a slice
-fn find_index_of_array(slice: &[u8], subslice: &[u8]) -> Option<usize> {
-    let mut i = 0;
-    while i <= slice.len() - subslice.len() {
-        if slice[i..i + subslice.len()].to_vec() == subslice.to_vec() {
-            return Some(i);
-        }
-        i += 1;
-    }
-    None
-}
-
-// return the common part of two slices starting from index 0
-fn common(slice: &[u8], subslice: &[u8]) -> Vec<u8> {
-    let mut i = 0;
-    while i < slice.len() && i < subslice.len() {
-        if slice[i] != subslice[i] {
-            break;
-        }
-        i += 1;
-    }
-    slice[0..i].to_vec()
-}
-
 impl Node {
     pub fn new_node_ref(ref_: &[u8]) -> Node {
         Node {
@@ -135,9 +111,9 @@ impl Node {
         self.node_type &= NT_MASK ^ NT_WITH_PATH_SEPARATOR
     }
 
-    // fn make_not_with_metadata(&mut self) {
-    //     self.node_type &= NT_MASK ^ NT_WITH_METADATA
-    // }
+    fn make_not_with_metadata(&mut self) {
+        self.node_type &= NT_MASK ^ NT_WITH_METADATA
+    }
 
     fn set_obfuscation_key(&mut self, key: &[u8]) {
         if key.len() != NODE_OBFUSCATION_KEY_SIZE {
@@ -153,7 +129,7 @@ impl Node {
         &mut self,
         path: &[u8],
         l: &mut Option<DynLoaderSaver>,
-    ) -> Result<&mut Node> {
+    ) -> Result<&'life_self mut Node> {
         // if forks hashmap is empty, perhaps we haven't loaded the forks yet
         if self.forks.is_empty() {
             self.load(l).await?;
@@ -171,11 +147,11 @@ impl Node {
             ),
             Some(f) => {
                 // get the common prefix of the fork and the path
-                let c = common(&f.prefix, path);
+                let c = longest_common_prefix(&f.prefix, path);
 
                 // if c is the same length as the fork prefix then recursive lookup node
-                if c.len() == f.prefix.len() {
-                    f.node.lookup_node(&path[c.len()..], l).await
+                if c == f.prefix.len() {
+                    f.node.lookup_node(&path[c..], l).await
                 } else {
                     Err(
                         Box::new(MantarayNodeError::NoForkForNode(hex::encode(&self.ref_)))
@@ -200,6 +176,44 @@ impl Node {
         Ok(node.entry.as_slice())
     }
 
+    // lookup_entry finds the entry and metadata at exactly path, or none if there is no value
+    // there. a path ending part way through a fork prefix, or at a node without a value, has
+    // no entry, just as a path for which there is no fork at all.
+    #[async_recursion]
+    pub async fn lookup_entry(
+        &mut self,
+        path: &[u8],
+        l: &mut Option<DynLoaderSaver>,
+    ) -> Result<Option<Entry>> {
+        if self.forks.is_empty() {
+            self.load(l).await?;
+        }
+
+        if path.is_empty() {
+            if !self.is_value_type() {
+                return Ok(None);
+            }
+
+            return Ok(Some(Entry {
+                reference: self.entry.clone(),
+                metadata: self.metadata.clone(),
+            }));
+        }
+
+        // no fork starts with the next byte of the path
+        let fork = match self.forks.get_mut(&path[0]) {
+            Some(fork) => fork,
+            None => return Ok(None),
+        };
+
+        // the path either diverges from the fork prefix or ends within it
+        if !path.starts_with(&fork.prefix) {
+            return Ok(None);
+        }
+
+        fork.node.lookup_entry(&path[fork.prefix.len()..], l).await
+    }
+
     // Add adds an entry to the path with metadata
     #[async_recursion]
     pub async fn add(
@@ -246,9 +260,12 @@ impl Node {
         // if forks hashmap is empty, perhaps we haven't loaded the forks yet
         if self.forks.is_empty() {
             self.load(ls).await?;
-            self.ref_ = vec![];
         }
 
+        // the node is changing, so its reference must be recomputed on the next save, even
+        // if it was loaded earlier by a lookup
+        self.ref_ = vec![];
+
         // try get the fork at the first character of the path
         let mut f = self.forks.get_mut(&path[0]);
         if f.is_none() {
@@ -307,8 +324,8 @@ impl Node {
         }
 
         // get the common prefix of the fork and the path, then get the rest of the path
-        let c = common(&f.as_ref().unwrap().prefix, path);
-        let rest = f.as_ref().unwrap().prefix[c.len()..].to_vec();
+        let c = longest_common_prefix(&f.as_ref().unwrap().prefix, path);
+        let rest = f.as_ref().unwrap().prefix[c..].to_vec();
 
         // get mutable reference to the fork node
         let mut nn = f.as_ref().unwrap().node.clone();
@@ -342,7 +359,7 @@ impl Node {
             nn.make_edge();
 
             // if common path is full path new node is value type
-            if c.len() == path.len() {
+            if c == path.len() {
                 nn.make_value();
             }
         }
@@ -350,13 +367,13 @@ impl Node {
         nn.update_is_with_path_separator(path);
 
         // add new node for shared prefix
-        nn.add(&path[c.len()..], entry, metadata, ls).await?;
+        nn.add(&path[c..], entry, metadata, ls).await?;
 
         // add the new node to the forks hashmap
         self.forks.insert(
             path[0],
             Fork {
-                prefix: c.to_vec(),
+                prefix: path[..c].to_vec(),
                 node: nn,
             },
         );
@@ -378,9 +395,11 @@ impl Node {
         self.make_not_with_path_separator();
     }
 
-    // remove removes a path from the node
+    // remove removes the entry at path from the node, returning whether there was one. nodes
+    // left without a value or forks are dropped, and a node left with no value and a single
+    // fork is merged with it, so the trie stays as add would have built it.
     #[async_recursion]
-    pub async fn remove(&mut self, path: &[u8], ls: &mut Option<DynLoaderSaver>) -> Result<()> {
+    pub async fn remove(&mut self, path: &[u8], ls: &mut Option<DynLoaderSaver>) -> Result<bool> {
         // if path is empty then return error
         if path.is_empty() {
             return Err(Box::new(MantarayNodeError::EmptyPath) as Box<dyn Error + Send>);
@@ -391,31 +410,68 @@ impl Node {
             self.load(ls).await?;
         }
 
-        // if path is not empty then get the fork at the first character of the path
-        let f = self.forks.get_mut(&path[0]);
-        if f.is_none() {
-            return Err(Box::new(MantarayNodeError::PathPrefixNotFound(
-                String::from_utf8(vec![path[0]]).unwrap(),
-            )) as Box<dyn Error + Send>);
-        }
+        // the path must continue through the whole prefix of the fork at its first character
+        let f = match self.forks.get_mut(&path[0]) {
+            Some(f) if path.starts_with(&f.prefix) => f,
+            _ => return Ok(false),
+        };
 
-        // returns the index of the first instance of sep in s, or -1 if sep is not present in s.
-        let prefix_index = find_index_of_array(path, &f.as_ref().unwrap().prefix);
-        if prefix_index.is_none() {
-            return Err(Box::new(MantarayNodeError::PathPrefixNotFound(
-                String::from_utf8(path.to_vec()).unwrap(),
-            )) as Box<dyn Error + Send>);
+        let rest = &path[f.prefix.len()..];
+        if rest.is_empty() {
+            // full path matched, the entry is held by the fork node
+            if !f.node.is_value_type() {
+                return Ok(false);
+            }
+
+            if f.node.forks.is_empty() {
+                f.node.load(ls).await?;
+            }
+
+            f.node.entry = vec![];
+            f.node.metadata = BTreeMap::new();
+            f.node.make_not_value();
+            f.node.make_not_with_metadata();
+            f.node.ref_ = vec![];
+        } else if !f.node.remove(rest, ls).await? {
+            return Ok(false);
         }
 
-        let rest = &path[f.as_ref().unwrap().prefix.len()..];
-        if rest.is_empty() {
-            // full path matched
-            self.forks.remove(&path[0]);
-            self.ref_ = vec![];
-            return Ok(());
+        self.ref_ = vec![];
+        self.compact_fork(path[0]);
+
+        Ok(true)
+    }
+
+    // compact_fork drops the fork at key if its node has neither a value nor forks, and merges
+    // a node without a value into its only fork if the joined prefix fits in a single fork.
+    fn compact_fork(&mut self, key: u8) {
+        let f = &self.forks[&key];
+        if f.node.is_value_type() {
+            return;
         }
 
-        f.unwrap().node.remove(rest, ls).await
+        match f.node.forks.len() {
+            0 => {
+                self.forks.remove(&key);
+            }
+            1 => {
+                let child = f.node.forks.values().next().unwrap();
+                if f.prefix.len() + child.prefix.len() > NODE_PREFIX_MAX_SIZE {
+                    return;
+                }
+
+                let mut f = self.forks.remove(&key).unwrap();
+                let child = f.node.forks.drain().next().unwrap().1;
+
+                let mut prefix = f.prefix;
+                prefix.extend_from_slice(&child.prefix);
+
+                let mut node = child.node;
+                node.update_is_with_path_separator(&prefix);
+                self.forks.insert(key, Fork { prefix, node });
+            }
+            _ => {}
+        }
     }
 
     // hasprefix tests whether the node contains prefix path
@@ -442,11 +498,11 @@ impl Node {
         }
 
         // returns the index of the first instance of sep in s, or -1 if sep is not present in s.
-        let c = common(&fork.as_ref().unwrap().prefix, path);
+        let c = longest_common_prefix(&fork.as_ref().unwrap().prefix, path);
 
         // if common prefix is full path then return true
-        if c.len() == fork.as_ref().unwrap().prefix.len() {
-            return fork.unwrap().node.has_prefix(&path[c.len()..], l).await;
+        if c == fork.as_ref().unwrap().prefix.len() {
+            return fork.unwrap().node.has_prefix(&path[c..], l).await;
         }
 
         // determine if a fork prefix begins with the byte slice t.
@@ -498,7 +554,7 @@ mod tests {
     #[tokio::test]
     async fn nil_path() {
         let mut n = Node::default();
-        assert_eq!(n.lookup("".as_bytes(), &mut None).await.is_ok(), true);
+        assert!(n.lookup("".as_bytes(), &mut None).await.is_ok());
     }
 
     // test data
@@ -662,7 +718,7 @@ mod tests {
             for j in 0..i {
                 let d = test_case_data()[0].items[j].as_bytes();
                 let r = n.lookup(d, &mut None).await;
-                assert_eq!(r.is_ok(), true);
+                assert!(r.is_ok());
                 let de = vec![0; 32 - d.len()]
                     .iter()
                     .chain(d.iter())
@@ -673,6 +729,95 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn lookup_entry() {
+        let mut n = Node::default();
+        let mut metadata = BTreeMap::new();
+        metadata.insert("Content-Type".to_string(), "image/png".to_string());
+
+        for c in test_case_data()[1].items.iter() {
+            n.add(
+                c.as_bytes(),
+                &[c.len() as u8; 32],
+                metadata.clone(),
+                &mut None,
+            )
+            .await
+            .unwrap();
+        }
+
+        // exact hit
+        let entry = n
+            .lookup_entry("img/1.png".as_bytes(), &mut None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.reference, vec![9; 32]);
+        assert_eq!(entry.metadata, metadata);
+
+        // the path ends within the fork prefix "img/" and at the node below it
+        assert!(n
+            .lookup_entry("im".as_bytes(), &mut None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(n
+            .lookup_entry("img/".as_bytes(), &mut None)
+            .await
+            .unwrap()
+            .is_none());
+
+        // no fork for the path at all
+        assert!(n
+            .lookup_entry("css/app.css".as_bytes(), &mut None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(n
+            .lookup_entry("img/3.png".as_bytes(), &mut None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    // add to a trie that was loaded from storage, splitting its forks, and check that every
+    // entry survives a save and reload
+    #[test_case(vec!["app.js.map"], "app.js" ; "new path is a prefix of the fork")]
+    #[test_case(vec!["aaaaaa"], "aaaaab" ; "common prefix shorter than both")]
+    #[test_case(vec!["app.js"], "app.js.map" ; "fork is a prefix of the new path")]
+    #[tokio::test]
+    async fn add_splits_loaded_forks(existing: Vec<&str>, added: &str) {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut n = Node::default();
+        for (i, c) in existing.iter().enumerate() {
+            n.add(c.as_bytes(), &[i as u8; 32], BTreeMap::new(), &mut None)
+                .await
+                .unwrap();
+        }
+        n.save(&Some(Box::new(ls.clone()))).await.unwrap();
+
+        // load the trie through a lookup before adding to it
+        let mut n = Node::new_node_ref(&n.ref_);
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(ls.clone()));
+        n.lookup(existing[0].as_bytes(), &mut l).await.unwrap();
+        n.add(added.as_bytes(), &[0xff; 32], BTreeMap::new(), &mut l)
+            .await
+            .unwrap();
+        n.save(&l).await.unwrap();
+
+        let mut n = Node::new_node_ref(&n.ref_);
+        for (i, c) in existing.iter().enumerate() {
+            let entry = n.lookup_entry(c.as_bytes(), &mut l).await.unwrap().unwrap();
+            assert_eq!(entry.reference, vec![i as u8; 32]);
+        }
+        let entry = n
+            .lookup_entry(added.as_bytes(), &mut l)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.reference, vec![0xff; 32]);
+    }
+
     #[test_case(test_case_data()[0].items.clone() ; "a")]
     #[test_case(test_case_data()[1].items.clone() ; "simple")]
     #[test_case(test_case_data()[2].items.clone() ; "nested-value-node-is-recognized")]
@@ -697,10 +842,9 @@ mod tests {
                 ()
             );
 
-            for j in 0..i {
-                let d = tc[j];
+            for d in tc.iter().take(i) {
                 let node = n.lookup_node(d.as_bytes(), &mut None).await.unwrap();
-                assert_eq!(node.is_value_type(), true);
+                assert!(node.is_value_type());
                 let de = vec![0; 32 - d.len()]
                     .iter()
                     .chain(d.as_bytes().iter())
@@ -739,7 +883,7 @@ mod tests {
         let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
 
         let save = n.save(&Some(Box::new(ls.clone()))).await;
-        assert_eq!(save.is_ok(), true);
+        assert!(save.is_ok());
 
         let mut n2 = Node::new_node_ref(&n.ref_);
 
@@ -748,7 +892,7 @@ mod tests {
                 .lookup_node(d.as_bytes(), &mut Some(Box::new(ls.clone())))
                 .await
                 .unwrap();
-            assert_eq!(node.is_value_type(), true);
+            assert!(node.is_value_type());
             let de = vec![0; 32 - d.len()]
                 .iter()
                 .chain(d.as_bytes().iter())
@@ -780,7 +924,7 @@ mod tests {
             for j in 0..i {
                 let d = &tc.items[j].path;
                 let r = n.lookup(d.as_bytes(), &mut None).await;
-                assert_eq!(r.is_ok(), true);
+                assert!(r.is_ok());
                 let de = vec![0; 32 - d.len()]
                     .iter()
                     .chain(d.as_bytes().iter())
@@ -792,10 +936,90 @@ mod tests {
 
         for c in tc.remove.iter() {
             // create a vector from the string c zero padded to the left to 32 bytes
-            assert_eq!(n.remove(c.as_bytes(), &mut None).await.unwrap(), ());
+            assert!(n.remove(c.as_bytes(), &mut None).await.unwrap());
+
+            assert!(n.lookup(c.as_bytes(), &mut None).await.is_err());
+            assert!(!n.remove(c.as_bytes(), &mut None).await.unwrap());
+        }
+
+        // every other entry is still there
+        for c in tc.items.iter().filter(|c| !tc.remove.contains(&c.path)) {
+            assert!(n.lookup(c.path.as_bytes(), &mut None).await.is_ok());
+        }
+    }
+
+    // build a trie, save it and return its reference
+    async fn saved_trie(paths: &[&str], ls: &Arc<Mutex<MockLoadSaver>>) -> Vec<u8> {
+        let mut n = Node::default();
+        for c in paths {
+            n.add(
+                c.as_bytes(),
+                &[c.len() as u8; 32],
+                BTreeMap::new(),
+                &mut None,
+            )
+            .await
+            .unwrap();
+        }
+        n.save(&Some(Box::new(ls.clone()))).await.unwrap();
+
+        n.ref_
+    }
 
-            assert_eq!(n.lookup(c.as_bytes(), &mut None).await.is_err(), true);
+    // strip the references and obfuscation keys from a loaded trie
+    fn without_refs(mut n: Node) -> Node {
+        n.ref_ = vec![];
+        n.obfuscation_key = vec![];
+        for fork in n.forks.values_mut() {
+            fork.node = without_refs(std::mem::take(&mut fork.node));
         }
+
+        n
+    }
+
+    // removing paths from a saved trie yields the same trie as never adding them
+    #[test_case(vec!["index.html", "img/1.png", "img/2.png"], vec!["img/2.png"] ; "leaf")]
+    #[test_case(vec!["app.js", "app.js.map"], vec!["app.js"] ; "internal value")]
+    #[test_case(vec!["aaaaaa", "aaaaab", "aa", "b"], vec!["aa", "aaaaab"] ; "merges forks")]
+    #[tokio::test]
+    async fn remove_is_canonical(paths: Vec<&str>, remove: Vec<&str>) {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(ls.clone()));
+
+        let mut n = Node::new_node_ref(&saved_trie(&paths, &ls).await);
+        for c in &remove {
+            assert!(n.remove(c.as_bytes(), &mut l).await.unwrap());
+        }
+        // a path ending within a fork prefix has no entry to remove
+        assert!(!n.remove("im".as_bytes(), &mut l).await.unwrap());
+        n.save(&l).await.unwrap();
+
+        let remaining: Vec<&str> = paths
+            .iter()
+            .filter(|c| !remove.contains(c))
+            .cloned()
+            .collect();
+        let mut n = Node::new_node_ref(&n.ref_);
+        crate::walker::walk_node(vec![], &mut l, &mut n)
+            .await
+            .unwrap();
+
+        // nodes are saved with random obfuscation keys, so compare the loaded tries
+        let mut expected = Node::new_node_ref(&saved_trie(&remaining, &ls).await);
+        crate::walker::walk_node(vec![], &mut l, &mut expected)
+            .await
+            .unwrap();
+        assert_eq!(without_refs(n.clone()), without_refs(expected));
+        let entries = n.entries_under(&[], &mut l).await.unwrap();
+        let mut expected: Vec<Vec<u8>> = remaining.iter().map(|c| c.as_bytes().to_vec()).collect();
+        expected.sort();
+        assert_eq!(
+            entries
+                .into_iter()
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>(),
+            expected
+        );
     }
 
     #[test_case(has_prefix_test_case_data()[0].clone() ; "simple")]
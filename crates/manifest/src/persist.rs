@@ -1,9 +1,11 @@
 use crate::Result;
 use async_recursion::async_recursion;
 use async_trait::async_trait;
-use bee_api::BeeConfig;
-use std::collections::HashMap;
+use lru::LruCache;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
 use std::fmt::Debug;
+use std::path::PathBuf;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::Mutex;
@@ -14,6 +16,24 @@ use crate::{keccak256, marshal::Marshal, node::Node};
 pub enum MantarayPersistError {
     #[error("No loader provided")]
     NoLoaderError,
+    #[error("Bee responded with status {0}")]
+    StatusError(u16),
+    #[error("Node of {0} bytes does not fit in a chunk")]
+    ChunkTooLargeError(usize),
+}
+
+// LoaderError is returned by loader/savers, so that callers can tell a missing node apart from
+// a failing storage backend by downcasting the boxed error.
+#[derive(Error, Debug)]
+pub enum LoaderError {
+    #[error("Node {} not found", hex::encode(.0))]
+    NotFound(Vec<u8>),
+    #[error("Io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid node data: {0}")]
+    Decode(String),
+    #[error("Backend error: {0}")]
+    Backend(#[source] Box<dyn Error + Send + Sync>),
 }
 
 pub type DynLoaderSaver = Box<dyn LoaderSaver + Send + Sync>;
@@ -30,6 +50,8 @@ pub trait Saver {
     async fn save(&self, data: &[u8]) -> Result<Vec<u8>>;
 }
 
+// LoaderSaver is object safe, so that a DynLoaderSaver can be shared across tasks. Implementations
+// report failures as a boxed LoaderError.
 #[async_trait]
 pub trait LoaderSaver: Debug + Sync {
     async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>>;
@@ -37,6 +59,23 @@ pub trait LoaderSaver: Debug + Sync {
     async fn as_dyn(&self) -> &dyn LoaderSaver;
 }
 
+// Sweeper lists and deletes the data held by a storage backend, for garbage collection.
+#[async_trait]
+pub trait Sweeper {
+    async fn addresses(&self) -> Result<Vec<Vec<u8>>>;
+    async fn delete(&self, ref_: &[u8]) -> Result<()>;
+}
+
+// PinStore records the roots whose reachable data must survive garbage collection, even when
+// they are not among the roots it is run with.
+#[async_trait]
+pub trait PinStore {
+    async fn pin(&self, root: &[u8]) -> Result<()>;
+    // unpin releases a pinned root, returning whether it was pinned.
+    async fn unpin(&self, root: &[u8]) -> Result<bool>;
+    async fn pins(&self) -> Result<Vec<Vec<u8>>>;
+}
+
 impl Node {
     // a load function for nodes
     pub async fn load(&mut self, l: &mut Option<DynLoaderSaver>) -> Result<()> {
@@ -99,13 +138,12 @@ pub type Address = [u8; 32];
 #[derive(Debug, Default)]
 pub struct MockLoadSaver {
     store: Arc<Mutex<HashMap<Address, Vec<u8>>>>,
+    pins: Arc<Mutex<BTreeSet<Vec<u8>>>>,
 }
 
 impl MockLoadSaver {
     pub fn new() -> MockLoadSaver {
-        MockLoadSaver {
-            store: Arc::new(Mutex::new(HashMap::new())),
-        }
+        MockLoadSaver::default()
     }
 }
 
@@ -117,8 +155,10 @@ impl LoaderSaver for MockLoadSaver {
 
     async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
         let store = self.store.lock().await;
-        let data = store.get(ref_).unwrap();
-        Ok(data.clone())
+        match store.get(ref_) {
+            Some(data) => Ok(data.clone()),
+            None => Err(Box::new(LoaderError::NotFound(ref_.to_vec()))),
+        }
     }
 
     async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
@@ -129,21 +169,231 @@ impl LoaderSaver for MockLoadSaver {
     }
 }
 
+#[async_trait]
+impl Sweeper for MockLoadSaver {
+    async fn addresses(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.store.lock().await.keys().map(|k| k.to_vec()).collect())
+    }
+
+    async fn delete(&self, ref_: &[u8]) -> Result<()> {
+        if let Ok(address) = Address::try_from(ref_) {
+            self.store.lock().await.remove(&address);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PinStore for MockLoadSaver {
+    async fn pin(&self, root: &[u8]) -> Result<()> {
+        self.pins.lock().await.insert(root.to_vec());
+        Ok(())
+    }
+
+    async fn unpin(&self, root: &[u8]) -> Result<bool> {
+        Ok(self.pins.lock().await.remove(root))
+    }
+
+    async fn pins(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.pins.lock().await.iter().cloned().collect())
+    }
+}
+
+// FsLoaderSaver stores each saved node as a file in root, named by the hex encoded keccak256
+// of its data, eg. for building and inspecting manifests locally.
+#[derive(Debug, Clone)]
+pub struct FsLoaderSaver {
+    pub root: PathBuf,
+}
+
+impl FsLoaderSaver {
+    pub fn new(root: impl Into<PathBuf>) -> FsLoaderSaver {
+        FsLoaderSaver { root: root.into() }
+    }
+
+    fn path(&self, ref_: &[u8]) -> PathBuf {
+        self.root.join(hex::encode(ref_))
+    }
+
+    // pinned roots are kept in a file of hex encoded references, one per line
+    fn pins_path(&self) -> PathBuf {
+        self.root.join(PINS_FILE)
+    }
+
+    fn read_pins(&self) -> Result<BTreeSet<Vec<u8>>> {
+        let pins = match std::fs::read_to_string(self.pins_path()) {
+            Ok(pins) => pins,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+            Err(e) => return Err(Box::new(LoaderError::Io(e))),
+        };
+
+        pins.lines()
+            .map(|line| {
+                hex::decode(line).map_err(|e| {
+                    Box::new(LoaderError::Decode(e.to_string())) as Box<dyn Error + Send>
+                })
+            })
+            .collect()
+    }
+
+    fn write_pins(&self, pins: &BTreeSet<Vec<u8>>) -> Result<()> {
+        let pins: String = pins.iter().map(|p| hex::encode(p) + "\n").collect();
+
+        std::fs::create_dir_all(&self.root)
+            .and_then(|_| std::fs::write(self.pins_path(), pins))
+            .map_err(|e| Box::new(LoaderError::Io(e)) as Box<dyn Error + Send>)
+    }
+}
+
+const PINS_FILE: &str = "pins";
+
+#[async_trait]
+impl LoaderSaver for FsLoaderSaver {
+    async fn as_dyn(&self) -> &dyn LoaderSaver {
+        self
+    }
+
+    async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
+        std::fs::read(self.path(ref_)).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Box::new(LoaderError::NotFound(ref_.to_vec())),
+            _ => Box::new(LoaderError::Io(e)) as Box<dyn Error + Send>,
+        })
+    }
+
+    async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let ref_ = keccak256(data);
+
+        std::fs::create_dir_all(&self.root)
+            .and_then(|_| std::fs::write(self.path(&ref_), data))
+            .map_err(|e| Box::new(LoaderError::Io(e)) as Box<dyn Error + Send>)?;
+
+        Ok(ref_.to_vec())
+    }
+}
+
+// every file in root named by a hex encoded reference is stored data, including chunks written
+// there by other tools, while other files are left alone.
+#[async_trait]
+impl Sweeper for FsLoaderSaver {
+    async fn addresses(&self) -> Result<Vec<Vec<u8>>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Box::new(LoaderError::Io(e))),
+        };
+
+        let mut addresses = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| Box::new(LoaderError::Io(e)) as Box<dyn Error + Send>)?;
+            if let Some(ref_) = entry.file_name().to_str().and_then(|n| hex::decode(n).ok()) {
+                addresses.push(ref_);
+            }
+        }
+
+        Ok(addresses)
+    }
+
+    async fn delete(&self, ref_: &[u8]) -> Result<()> {
+        match std::fs::remove_file(self.path(ref_)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(Box::new(LoaderError::Io(e))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl PinStore for FsLoaderSaver {
+    async fn pin(&self, root: &[u8]) -> Result<()> {
+        let mut pins = self.read_pins()?;
+        if pins.insert(root.to_vec()) {
+            self.write_pins(&pins)?;
+        }
+        Ok(())
+    }
+
+    async fn unpin(&self, root: &[u8]) -> Result<bool> {
+        let mut pins = self.read_pins()?;
+        let pinned = pins.remove(root);
+        if pinned {
+            self.write_pins(&pins)?;
+        }
+        Ok(pinned)
+    }
+
+    async fn pins(&self) -> Result<Vec<Vec<u8>>> {
+        Ok(self.read_pins()?.into_iter().collect())
+    }
+}
+
+// CachingLoaderSaver wraps another loader/saver with an lru cache of node data by reference,
+// so that repeated loads of the same node are served from memory rather than the backend.
+// Saved nodes are cached as well.
 #[derive(Debug)]
+pub struct CachingLoaderSaver<L> {
+    inner: L,
+    cache: Mutex<LruCache<Vec<u8>, Vec<u8>>>,
+}
+
+impl<L> CachingLoaderSaver<L> {
+    pub fn new(inner: L, capacity: usize) -> CachingLoaderSaver<L> {
+        CachingLoaderSaver {
+            inner,
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<L: LoaderSaver + Send> LoaderSaver for CachingLoaderSaver<L> {
+    async fn as_dyn(&self) -> &dyn LoaderSaver {
+        self
+    }
+
+    async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
+        if let Some(data) = self.cache.get_mut().get(ref_) {
+            return Ok(data.clone());
+        }
+
+        let data = self.inner.load(ref_).await?;
+        self.cache.get_mut().put(ref_.to_vec(), data.clone());
+        Ok(data)
+    }
+
+    async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let ref_ = self.inner.save(data).await?;
+        self.cache.lock().await.put(ref_.clone(), data.to_vec());
+        Ok(ref_)
+    }
+}
+
+// BeeLoadSaver stores each node as a file through the /bytes endpoint of a Bee node, stamped
+// with batch_id when one is given. As Bee splits the data into chunks itself, nodes of any size
+// can be saved, and references are the root addresses of the uploaded files.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
 pub struct BeeLoadSaver {
     pub uri: String,
-    pub config: BeeConfig,
+    pub batch_id: Option<BatchId>,
     pub client: reqwest::Client,
 }
 
+#[cfg(feature = "http")]
 impl BeeLoadSaver {
-    pub fn new(uri: String, config: BeeConfig) -> BeeLoadSaver {
+    pub fn new(uri: String, batch_id: Option<BatchId>) -> BeeLoadSaver {
         BeeLoadSaver {
             uri,
-            config,
+            batch_id,
             client: reqwest::Client::new(),
         }
     }
+
+    fn bytes_url(&self) -> String {
+        format!("{}/bytes", self.uri.trim_end_matches('/'))
+    }
 }
 
 // #[async_trait]
@@ -161,6 +411,7 @@ impl BeeLoadSaver {
 //     }
 // }
 
+#[cfg(feature = "http")]
 #[async_trait]
 impl LoaderSaver for Arc<BeeLoadSaver> {
     async fn as_dyn(&self) -> &dyn LoaderSaver {
@@ -168,30 +419,146 @@ impl LoaderSaver for Arc<BeeLoadSaver> {
     }
 
     async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
-        Ok(
-            bee_api::bytes_get(&self.client, self.uri.clone(), hex::encode(ref_))
-                .await?
-                .0,
-        )
+        let url = format!("{}/{}", self.bytes_url(), hex::encode(ref_));
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(BeeLoaderSaver::backend)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Box::new(LoaderError::NotFound(ref_.to_vec())));
+        }
+
+        BeeLoaderSaver::body(response).await
     }
 
     async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
-        match hex::decode(
-            bee_api::bytes_post(
-                &self.client,
-                self.uri.clone(),
-                data.to_vec(),
-                self.config
-                    .upload
-                    .as_ref()
-                    .expect("UploadConfig not specified"),
-            )
-            .await?
-            .ref_,
-        ) {
-            Ok(ref_) => Ok(ref_),
-            Err(e) => Err(Box::new(e)),
+        let mut request = self
+            .client
+            .post(self.bytes_url())
+            .header("Content-Type", "application/octet-stream")
+            .body(data.to_vec());
+        if let Some(batch_id) = self.batch_id {
+            request = request.header("Swarm-Postage-Batch-Id", hex::encode(batch_id));
         }
+
+        let response = request.send().await.map_err(BeeLoaderSaver::backend)?;
+        let body = BeeLoaderSaver::body(response).await?;
+        let response: ReferenceResponse = serde_json::from_slice(&body)
+            .map_err(|e| Box::new(LoaderError::Decode(e.to_string())) as Box<dyn Error + Send>)?;
+
+        hex::decode(response.reference)
+            .map_err(|e| Box::new(LoaderError::Decode(e.to_string())) as Box<dyn Error + Send>)
+    }
+}
+
+#[cfg(feature = "http")]
+pub use nectar_primitives_traits::BatchId;
+
+// size of the little-endian span prefixed to a chunk payload
+#[cfg(feature = "http")]
+const SPAN_SIZE: usize = 8;
+
+// maximum payload size of a single chunk
+#[cfg(feature = "http")]
+const CHUNK_SIZE: usize = 4096;
+
+#[cfg(feature = "http")]
+#[derive(serde::Deserialize)]
+struct ReferenceResponse {
+    reference: String,
+}
+
+// BeeLoaderSaver stores each node as a single content addressed chunk through the /chunks
+// endpoint of a Bee node, stamped with batch_id when one is given. Unlike BeeLoadSaver, which
+// goes through /bytes, a node must fit in one chunk payload and references are chunk addresses.
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct BeeLoaderSaver {
+    pub endpoint: reqwest::Url,
+    pub batch_id: Option<BatchId>,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "http")]
+impl BeeLoaderSaver {
+    pub fn new(endpoint: reqwest::Url, batch_id: Option<BatchId>) -> BeeLoaderSaver {
+        BeeLoaderSaver {
+            endpoint,
+            batch_id,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn chunks_url(&self) -> String {
+        format!("{}/chunks", self.endpoint.as_str().trim_end_matches('/'))
+    }
+
+    fn backend(e: impl Error + Send + Sync + 'static) -> Box<dyn Error + Send> {
+        Box::new(LoaderError::Backend(Box::new(e)))
+    }
+
+    // read the body of a successful response, mapping any other status to a backend error
+    async fn body(response: reqwest::Response) -> Result<Vec<u8>> {
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Self::backend(MantarayPersistError::StatusError(
+                status.as_u16(),
+            )));
+        }
+
+        Ok(response.bytes().await.map_err(Self::backend)?.to_vec())
+    }
+}
+
+#[cfg(feature = "http")]
+#[async_trait]
+impl LoaderSaver for BeeLoaderSaver {
+    async fn as_dyn(&self) -> &dyn LoaderSaver {
+        self
+    }
+
+    async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
+        let url = format!("{}/{}", self.chunks_url(), hex::encode(ref_));
+        let response = self.client.get(url).send().await.map_err(Self::backend)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Box::new(LoaderError::NotFound(ref_.to_vec())));
+        }
+
+        let mut chunk = Self::body(response).await?;
+        if chunk.len() < SPAN_SIZE {
+            return Err(Box::new(LoaderError::Decode(format!(
+                "chunk of {} bytes",
+                chunk.len()
+            ))));
+        }
+
+        Ok(chunk.split_off(SPAN_SIZE))
+    }
+
+    async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
+        if data.len() > CHUNK_SIZE {
+            return Err(Box::new(MantarayPersistError::ChunkTooLargeError(
+                data.len(),
+            )));
+        }
+
+        let mut chunk = (data.len() as u64).to_le_bytes().to_vec();
+        chunk.extend_from_slice(data);
+
+        let mut request = self.client.post(self.chunks_url()).body(chunk);
+        if let Some(batch_id) = self.batch_id {
+            request = request.header("Swarm-Postage-Batch-Id", hex::encode(batch_id));
+        }
+
+        let response = request.send().await.map_err(Self::backend)?;
+        let body = Self::body(response).await?;
+        let response: ReferenceResponse = serde_json::from_slice(&body)
+            .map_err(|e| Box::new(LoaderError::Decode(e.to_string())) as Box<dyn Error + Send>)?;
+
+        hex::decode(response.reference)
+            .map_err(|e| Box::new(LoaderError::Decode(e.to_string())) as Box<dyn Error + Send>)
     }
 }
 
@@ -225,6 +592,32 @@ impl LoaderSaver for Arc<Mutex<MockLoadSaver>> {
     }
 }
 
+#[async_trait]
+impl Sweeper for Arc<Mutex<MockLoadSaver>> {
+    async fn addresses(&self) -> Result<Vec<Vec<u8>>> {
+        self.lock().await.addresses().await
+    }
+
+    async fn delete(&self, ref_: &[u8]) -> Result<()> {
+        self.lock().await.delete(ref_).await
+    }
+}
+
+#[async_trait]
+impl PinStore for Arc<Mutex<MockLoadSaver>> {
+    async fn pin(&self, root: &[u8]) -> Result<()> {
+        self.lock().await.pin(root).await
+    }
+
+    async fn unpin(&self, root: &[u8]) -> Result<bool> {
+        self.lock().await.unpin(root).await
+    }
+
+    async fn pins(&self) -> Result<Vec<Vec<u8>>> {
+        self.lock().await.pins().await
+    }
+}
+
 // tests
 #[cfg(test)]
 mod tests {
@@ -232,9 +625,176 @@ mod tests {
 
     use super::*;
 
-    use std::{collections::BTreeMap, sync::Arc};
+    use std::{
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
     use tokio::sync::Mutex;
 
+    #[tokio::test]
+    async fn mock_load_saver_round_trip() {
+        let mut ls = MockLoadSaver::new();
+        let data = b"a node or any other chunk of data".to_vec();
+
+        let reference = ls.save(&data).await.unwrap();
+        assert_eq!(reference, keccak256(&data).to_vec());
+        assert_eq!(ls.load(&reference).await.unwrap(), data);
+
+        // the same data is saved under the same reference
+        assert_eq!(ls.save(&data).await.unwrap(), reference);
+
+        let err = ls.load(&[0u8; 32]).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LoaderError>(),
+            Some(LoaderError::NotFound(_))
+        ));
+    }
+
+    #[derive(Debug)]
+    struct CountingLoadSaver {
+        inner: MockLoadSaver,
+        loads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LoaderSaver for CountingLoadSaver {
+        async fn as_dyn(&self) -> &dyn LoaderSaver {
+            self
+        }
+
+        async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            self.inner.load(ref_).await
+        }
+
+        async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
+            self.inner.save(data).await
+        }
+    }
+
+    #[tokio::test]
+    async fn caching_loader_saver() {
+        let inner = MockLoadSaver::new();
+        let a = inner.save(b"node a").await.unwrap();
+        let b = inner.save(b"node b").await.unwrap();
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let mut ls = CachingLoaderSaver::new(
+            CountingLoadSaver {
+                inner,
+                loads: loads.clone(),
+            },
+            1,
+        );
+
+        // the second load of a reference is served from the cache
+        assert_eq!(ls.load(&a).await.unwrap(), b"node a");
+        assert_eq!(ls.load(&a).await.unwrap(), b"node a");
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        // loading b evicts a at capacity 1
+        assert_eq!(ls.load(&b).await.unwrap(), b"node b");
+        assert_eq!(ls.load(&a).await.unwrap(), b"node a");
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+
+        // saved data is cached
+        let c = ls.save(b"node c").await.unwrap();
+        assert_eq!(ls.load(&c).await.unwrap(), b"node c");
+        assert_eq!(loads.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn fs_pins_persist() {
+        let root = std::env::temp_dir().join(format!("manifest-pins-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let ls = FsLoaderSaver::new(&root);
+        ls.pin(&[1; 32]).await.unwrap();
+        ls.pin(&[2; 32]).await.unwrap();
+        ls.pin(&[1; 32]).await.unwrap();
+
+        // pins are read back by another loader/saver over the same directory
+        let ls = FsLoaderSaver::new(&root);
+        assert_eq!(ls.pins().await.unwrap(), vec![vec![1; 32], vec![2; 32]]);
+        assert!(ls.unpin(&[1; 32]).await.unwrap());
+        assert!(!ls.unpin(&[3; 32]).await.unwrap());
+        assert_eq!(ls.pins().await.unwrap(), vec![vec![2; 32]]);
+
+        // the pins file is not stored data
+        assert!(ls.addresses().await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fs_load_saver_round_trip() {
+        let root = std::env::temp_dir().join(format!("manifest-fs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let ls = FsLoaderSaver::new(root.join("nodes"));
+        let mut m = Manifest::new(Box::new(ls.clone()), false);
+        let paths = ["index.html", "img/1.png", "img/2.png"];
+        for (i, path) in paths.iter().enumerate() {
+            m.add(
+                path,
+                Entry {
+                    reference: vec![i as u8 + 1; 32],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let reference = m.store().await.unwrap();
+        assert!(root.join("nodes").join(hex::encode(&reference)).is_file());
+
+        let mut m = Manifest::new_manifest_reference(reference, Box::new(ls.clone())).unwrap();
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(
+                m.lookup(path).await.unwrap().reference,
+                vec![i as u8 + 1; 32]
+            );
+        }
+
+        // a missing node is not found
+        let mut ls = ls;
+        let err = ls.load(&[0u8; 32]).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LoaderError>(),
+            Some(LoaderError::NotFound(r)) if r == &[0u8; 32]
+        ));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // requires a local Bee node, eg. bee dev, and a usable postage batch:
+    // BEE_BATCH_ID=<batch id> cargo test -- --ignored bee_loader_saver_round_trip
+    #[cfg(feature = "http")]
+    #[tokio::test]
+    #[ignore]
+    async fn bee_loader_saver_round_trip() {
+        let endpoint =
+            std::env::var("BEE_API_URL").unwrap_or_else(|_| "http://localhost:1633".to_string());
+        let batch_id: BatchId = std::env::var("BEE_BATCH_ID").unwrap().parse().unwrap();
+
+        let mut ls = BeeLoaderSaver::new(endpoint.parse().unwrap(), Some(batch_id));
+        let data = b"a node or any other chunk of data".to_vec();
+
+        let reference = ls.save(&data).await.unwrap();
+        assert_eq!(reference.len(), 32);
+        assert_eq!(ls.load(&reference).await.unwrap(), data);
+
+        // an unknown chunk is not found
+        let err = ls.load(&[0u8; 32]).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<LoaderError>(),
+            Some(LoaderError::NotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn persist_idempotence() {
         let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
@@ -274,4 +834,73 @@ mod tests {
             assert_eq!(entry.reference, v);
         }
     }
+
+    #[tokio::test]
+    async fn persist_metadata() {
+        let ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(ls.clone()), false);
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("Content-Type".to_string(), "text/html".to_string());
+        metadata.insert("Filename".to_string(), "index.html".to_string());
+
+        m.add(
+            "index.html",
+            Entry {
+                reference: vec![1; 32],
+                metadata: metadata.clone(),
+            },
+        )
+        .await
+        .unwrap();
+        m.add(
+            "robots.txt",
+            Entry {
+                reference: vec![2; 32],
+                metadata: BTreeMap::new(),
+            },
+        )
+        .await
+        .unwrap();
+        let reference = m.store().await.unwrap();
+
+        let mut m = Manifest::new_manifest_reference(reference, Box::new(ls)).unwrap();
+        let entry = m.lookup("index.html").await.unwrap();
+        assert_eq!(entry.reference, vec![1; 32]);
+        assert_eq!(entry.metadata, metadata);
+
+        let entry = m.lookup("robots.txt").await.unwrap();
+        assert!(entry.metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn persist_obfuscated() {
+        let metadata: BTreeMap<String, String> =
+            [("Content-Type".to_string(), "text/html".to_string())].into();
+        let cleartext = serde_json::to_vec(&metadata).unwrap();
+
+        for key in [[0u8; 32], [7u8; 32]] {
+            let mut ls = Arc::new(Mutex::new(MockLoadSaver::new()));
+            let mut m = Manifest::new_with_obfuscation_key(Box::new(ls.clone()), key);
+            m.add(
+                "index.html",
+                Entry {
+                    reference: vec![1; 32],
+                    metadata: metadata.clone(),
+                },
+            )
+            .await
+            .unwrap();
+            let reference = m.store().await.unwrap();
+
+            // the metadata is held in the root node, readable only without a key
+            let root = ls.load(&reference).await.unwrap();
+            assert_eq!(&root[..32], key.as_slice());
+            let readable = root.windows(cleartext.len()).any(|w| w == cleartext);
+            assert_eq!(readable, key == [0u8; 32]);
+
+            let mut m = Manifest::new_manifest_reference(reference, Box::new(ls)).unwrap();
+            assert_eq!(m.lookup("index.html").await.unwrap().metadata, metadata);
+        }
+    }
 }
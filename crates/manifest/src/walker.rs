@@ -1,24 +1,419 @@
 use async_recursion::async_recursion;
 
-use crate::{persist::DynLoaderSaver, Node, Result};
+use crate::{
+    gc, longest_common_prefix,
+    persist::{DynLoaderSaver, MantarayPersistError},
+    Entry, MantarayError, Node, Result,
+};
 
-#[async_recursion]
 pub async fn walk_node(path: Vec<u8>, l: &mut Option<DynLoaderSaver>, n: &mut Node) -> Result<()> {
+    walk_with(path, l, n, &mut |_, _| {}).await
+}
+
+// walk_with loads every node under n, calling visitor with the accumulated path of each node
+// once it has been loaded, parents before their forks.
+#[async_recursion]
+pub async fn walk_with<F>(
+    path: Vec<u8>,
+    l: &mut Option<DynLoaderSaver>,
+    n: &mut Node,
+    visitor: &mut F,
+) -> Result<()>
+where
+    F: FnMut(&[u8], &Node) + Send,
+{
     if n.forks.is_empty() {
         n.load(l).await?;
     }
 
-    // err := walkNodeFnCopyBytes(ctx, path, n, nil, walkFn)
-    // if err != nil {
-    // 	return err
-    // }
+    visitor(&path, n);
 
     for (_, v) in n.forks.iter_mut() {
         let mut next_path = path.clone();
         next_path.extend_from_slice(&v.prefix);
 
-        walk_node(next_path, l, &mut v.node).await?
+        walk_with(next_path, l, &mut v.node, visitor).await?
     }
 
     Ok(())
 }
+
+impl Node {
+    // entries collects the full path and entry of every value node in the trie, sorted by
+    // path, eg. to list the files of a directory upload.
+    pub async fn entries(
+        &mut self,
+        l: &mut Option<DynLoaderSaver>,
+    ) -> Result<Vec<(Vec<u8>, Entry)>> {
+        self.entries_under(&[], l).await
+    }
+
+    // entries_under collects the path and entry of every value node whose path starts with
+    // prefix, sorted by path. only forks that can lead to the prefix are descended into, so
+    // unrelated subtrees are never loaded.
+    pub async fn entries_under(
+        &mut self,
+        prefix: &[u8],
+        l: &mut Option<DynLoaderSaver>,
+    ) -> Result<Vec<(Vec<u8>, Entry)>> {
+        let mut entries = Vec::new();
+        self.collect_entries_under(Vec::new(), prefix, l, &mut entries)
+            .await?;
+
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(entries)
+    }
+
+    // reachable_addresses collects the reference of this node and of everything reachable from
+    // it, deduplicated and sorted: the manifest nodes under it, the entry of every value node
+    // and the chunks of the files' trees, walked as gc walks them. the node must have been
+    // stored, and references missing from l are listed without being descended into.
+    pub async fn reachable_addresses(
+        &mut self,
+        l: &mut Option<DynLoaderSaver>,
+    ) -> Result<Vec<Vec<u8>>> {
+        if self.ref_.is_empty() {
+            return Err(Box::new(MantarayError::NotFound(
+                "reference of an unsaved node".to_string(),
+            )));
+        }
+
+        let l = match l.as_mut() {
+            Some(l) => l,
+            None => return Err(Box::new(MantarayPersistError::NoLoaderError)),
+        };
+        let mut addresses: Vec<Vec<u8>> = gc::reachable(vec![self.ref_.clone()], l.as_mut())
+            .await?
+            .into_iter()
+            .collect();
+
+        addresses.sort();
+        Ok(addresses)
+    }
+
+    #[async_recursion]
+    async fn collect_entries_under(
+        &mut self,
+        path: Vec<u8>,
+        prefix: &[u8],
+        l: &mut Option<DynLoaderSaver>,
+        entries: &mut Vec<(Vec<u8>, Entry)>,
+    ) -> Result<()> {
+        if self.forks.is_empty() {
+            self.load(l).await?;
+        }
+
+        // the prefix has been consumed, everything from here on is under it
+        if prefix.is_empty() {
+            if self.is_value_type() && !path.is_empty() {
+                entries.push((
+                    path.clone(),
+                    Entry {
+                        reference: self.entry.clone(),
+                        metadata: self.metadata.clone(),
+                    },
+                ));
+            }
+
+            for fork in self.forks.values_mut() {
+                let mut next_path = path.clone();
+                next_path.extend_from_slice(&fork.prefix);
+
+                fork.node
+                    .collect_entries_under(next_path, prefix, l, entries)
+                    .await?;
+            }
+
+            return Ok(());
+        }
+
+        // only the fork starting with the next byte of the prefix can lead to it
+        let fork = match self.forks.get_mut(&prefix[0]) {
+            Some(fork) => fork,
+            None => return Ok(()),
+        };
+
+        // the fork must either be consumed by the prefix or end with the rest of it
+        let c = longest_common_prefix(&fork.prefix, prefix);
+        if c != fork.prefix.len() && c != prefix.len() {
+            return Ok(());
+        }
+
+        let mut next_path = path;
+        next_path.extend_from_slice(&fork.prefix);
+
+        fork.node
+            .collect_entries_under(next_path, &prefix[c..], l, entries)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        persist::{LoaderError, LoaderSaver, MockLoadSaver},
+        Manifest,
+    };
+
+    #[derive(Debug)]
+    struct CountingLoadSaver {
+        inner: Arc<Mutex<MockLoadSaver>>,
+        loads: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl LoaderSaver for CountingLoadSaver {
+        async fn as_dyn(&self) -> &dyn LoaderSaver {
+            self
+        }
+
+        async fn load(&mut self, ref_: &[u8]) -> Result<Vec<u8>> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            self.inner.lock().await.load(ref_).await
+        }
+
+        async fn save(&self, data: &[u8]) -> Result<Vec<u8>> {
+            self.inner.lock().await.save(data).await
+        }
+    }
+
+    #[tokio::test]
+    async fn entries_under_prefix() {
+        let store = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(store.clone()), false);
+
+        let paths = [
+            "docs/a.md",
+            "docs/b.md",
+            "docs.txt",
+            "img/1.png",
+            "img/2.png",
+            "index.html",
+        ];
+        for path in paths {
+            let mut reference = path.as_bytes().to_vec();
+            reference.resize(32, 0);
+            m.add(
+                path,
+                Entry {
+                    reference,
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let reference = m.store().await.unwrap();
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(CountingLoadSaver {
+            inner: store,
+            loads: loads.clone(),
+        }));
+
+        let mut root = Node::new_node_ref(&reference);
+        let entries = root.entries_under(b"docs/", &mut l).await.unwrap();
+        let partial_loads = loads.swap(0, Ordering::SeqCst);
+
+        let found: Vec<&[u8]> = entries.iter().map(|(p, _)| p.as_slice()).collect();
+        assert_eq!(found, vec![b"docs/a.md".as_slice(), b"docs/b.md"]);
+        for (path, entry) in &entries {
+            assert!(entry.reference.starts_with(path));
+        }
+
+        // a full walk loads every node, the prefix walk skips the unrelated subtrees
+        let mut root = Node::new_node_ref(&reference);
+        let entries = root.entries_under(b"", &mut l).await.unwrap();
+        assert_eq!(entries.len(), paths.len());
+        assert!(partial_loads < loads.load(Ordering::SeqCst));
+
+        let mut root = Node::new_node_ref(&reference);
+        assert!(root
+            .entries_under(b"none", &mut l)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn entries() {
+        let store = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(store.clone()), false);
+
+        let paths = [
+            "robots.txt",
+            "index.html",
+            "img/2.png",
+            "img/1.png",
+            "app.js.map",
+            "app.js",
+        ];
+        for (i, path) in paths.iter().enumerate() {
+            let mut metadata = BTreeMap::new();
+            metadata.insert("Filename".to_string(), path.to_string());
+            m.add(
+                path,
+                Entry {
+                    reference: vec![i as u8; 32],
+                    metadata,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let reference = m.store().await.unwrap();
+
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(store));
+        let mut root = Node::new_node_ref(&reference);
+        let entries = root.entries(&mut l).await.unwrap();
+
+        let mut expected: Vec<(Vec<u8>, Vec<u8>)> = paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| (path.as_bytes().to_vec(), vec![i as u8; 32]))
+            .collect();
+        expected.sort();
+
+        assert_eq!(
+            entries
+                .iter()
+                .map(|(path, entry)| (path.clone(), entry.reference.clone()))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        for (path, entry) in &entries {
+            assert_eq!(entry.metadata["Filename"].as_bytes(), path.as_slice());
+        }
+    }
+
+    #[tokio::test]
+    async fn walk_with_visitor() {
+        let store = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(store.clone()), false);
+
+        let paths = ["index.html", "img/1.png", "img/2.png"];
+        for (i, path) in paths.iter().enumerate() {
+            m.add(
+                path,
+                Entry {
+                    reference: vec![i as u8 + 1; 32],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let reference = m.store().await.unwrap();
+
+        let mut nodes = 0;
+        let mut leaves = Vec::new();
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(store));
+        let mut root = Node::new_node_ref(&reference);
+        walk_with(vec![], &mut l, &mut root, &mut |path, node| {
+            nodes += 1;
+            if node.forks.is_empty() {
+                leaves.push(path.to_vec());
+            }
+        })
+        .await
+        .unwrap();
+
+        // the root, "i", "ndex.html", "mg/", "1.png" and "2.png"
+        assert_eq!(nodes, 6);
+        leaves.sort();
+        assert_eq!(
+            leaves,
+            vec![
+                b"img/1.png".to_vec(),
+                b"img/2.png".to_vec(),
+                b"index.html".to_vec()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn walk_node_not_found() {
+        let store = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(store.clone()), false);
+        for path in ["index.html", "img/1.png"] {
+            m.add(
+                path,
+                Entry {
+                    reference: vec![1; 32],
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let reference = m.store().await.unwrap();
+
+        // a store holding only the root node
+        let partial = MockLoadSaver::new();
+        partial
+            .save(&store.lock().await.load(&reference).await.unwrap())
+            .await
+            .unwrap();
+
+        // the boxed loader can be moved into a spawned task
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(partial));
+        let mut root = Node::new_node_ref(&reference);
+        let err = tokio::spawn(async move { walk_node(vec![], &mut l, &mut root).await })
+            .await
+            .unwrap()
+            .unwrap_err();
+
+        match err.downcast_ref::<LoaderError>() {
+            Some(LoaderError::NotFound(missing)) => assert_ne!(missing, &reference),
+            _ => panic!("expected a not found error, got {err}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn reachable_addresses() {
+        let store = Arc::new(Mutex::new(MockLoadSaver::new()));
+        let mut m = Manifest::new(Box::new(store.clone()), false);
+
+        let files = [("index.html", [1u8; 32]), ("img/logo.png", [2u8; 32])];
+        for (path, reference) in files {
+            m.add(
+                path,
+                Entry {
+                    reference: reference.to_vec(),
+                    metadata: BTreeMap::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let reference = m.store().await.unwrap();
+
+        let loads = Arc::new(AtomicUsize::new(0));
+        let mut l: Option<DynLoaderSaver> = Some(Box::new(CountingLoadSaver {
+            inner: store,
+            loads: loads.clone(),
+        }));
+
+        let mut root = Node::new_node_ref(&reference);
+        let addresses = root.reachable_addresses(&mut l).await.unwrap();
+
+        // the manifest root, every other node and both file roots, each loaded once
+        assert!(addresses.contains(&reference));
+        for (_, file) in files {
+            assert!(addresses.contains(&file.to_vec()));
+        }
+        assert_eq!(addresses.len(), loads.load(Ordering::SeqCst));
+    }
+}
@@ -1,27 +1,118 @@
-use ethers_core::types::Address;
+use alloy::primitives::{Address, U256};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
 use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tracing::error;
 
-use crate::stamp::{MarshalledStamp, Stamp, StampError, StampValidator, ValidateStamp};
-use bmt::chunk::Chunk;
+use crate::pat::Pat;
+use crate::stamp::{MarshalledStamp, PostageStamp, StampError, StampValidator, ValidateStamp};
+use file::chunk::Chunk;
+use nectar_primitives_traits::Stamp;
 
 pub type BatchId = [u8; 32];
 
+/// Parse a batch id from hex, with or without a `0x` prefix, as shown by Bee and block explorers.
+pub fn parse_batch_id(s: &str) -> Result<BatchId, BatchError> {
+    hex::decode(s.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| BatchError::InvalidBatchId(s.to_string()))
+}
+
+/// The maximum payload size of a chunk
+pub const CHUNK_SIZE: u64 = 4096;
+
+/// A postage batch. Serialises to the same JSON shape as a batch in Bee's `/stamps` API.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Batch {
-    pub id: BatchId,                // the batch id
-    value: u128,                    // normalised balance of the batch
+    #[serde(rename = "batchID", with = "hex_batch_id")]
+    pub id: BatchId, // the batch id
+    #[serde(rename = "amount", with = "u128_string")]
+    value: u128, // normalised balance of the batch
+    #[serde(rename = "blockNumber")]
     pub block_created: Option<u64>, // block number the batch was created
-    pub(crate) owner: Address,      // owner of the batch
-    pub depth: u32,                 // depth of the batch
-    pub bucket_depth: u32,          // depth of the bucket
-    pub immutable: bool,            // whether the batch is immutable
+    #[serde(default)]
+    pub(crate) owner: Address, // owner of the batch
+    pub depth: u32,        // depth of the batch
+    pub bucket_depth: u32, // depth of the bucket
+    #[serde(rename = "immutableFlag")]
+    pub immutable: bool, // whether the batch is immutable
+}
+
+/// How long a batch should last, either in blocks or in time at a given block time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duration {
+    Blocks(u64),
+    /// `secs` seconds at `block_time` seconds per block
+    Time {
+        secs: u64,
+        block_time: u64,
+    },
+}
+
+impl Duration {
+    /// The number of blocks covering the duration. Time is rounded up to whole blocks, so that
+    /// a batch paid for this many blocks lasts at least as long as requested.
+    ///
+    /// Panics if the block time is zero.
+    pub fn to_blocks(&self) -> u64 {
+        match *self {
+            Duration::Blocks(blocks) => blocks,
+            Duration::Time { secs, block_time } => secs.div_ceil(block_time),
+        }
+    }
+}
+
+/// The decoded fields of the postage stamp contract's `BatchCreated` event
+#[derive(Debug, Clone)]
+pub struct BatchCreated {
+    pub batch_id: BatchId,
+    pub total_amount: U256,
+    pub normalised_balance: U256,
+    pub owner: Address,
+    pub depth: u8,
+    pub bucket_depth: u8,
+    pub immutable: bool,
+}
+
+/// (De)serialise a batch id as an unprefixed hex string
+mod hex_batch_id {
+    use super::BatchId;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(id: &BatchId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(id))
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BatchId, D::Error> {
+        super::parse_batch_id(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// (De)serialise a `u128` as a decimal string, as it may exceed the precision of JSON numbers
+mod u128_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
 }
 
 impl Batch {
+    /// Create a batch, rejecting a bucket depth greater than its depth.
     pub fn new(
         id: BatchId,
         value: u128,
@@ -30,8 +121,12 @@ impl Batch {
         depth: u32,
         bucket_depth: u32,
         immutable: bool,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, BatchError> {
+        if bucket_depth > depth {
+            return Err(BatchError::InvalidBucketDepth(depth, bucket_depth));
+        }
+
+        Ok(Self {
             id,
             value,
             block_created: start,
@@ -39,7 +134,27 @@ impl Batch {
             depth,
             bucket_depth,
             immutable,
-        }
+        })
+    }
+
+    /// Create a batch from a `BatchCreated` event of the postage stamp contract, emitted in
+    /// `block_number`. The value of the batch is the event's normalised balance.
+    pub fn from_event(event: &BatchCreated, block_number: u64) -> Result<Self, BatchError> {
+        let value = u128::try_from(event.normalised_balance)
+            .map_err(|_| BatchError::ValueOverflow(event.normalised_balance))?;
+
+        let batch = Self::new(
+            event.batch_id,
+            value,
+            Some(block_number),
+            event.owner,
+            event.depth as u32,
+            event.bucket_depth as u32,
+            event.immutable,
+        )?;
+        batch.max_collisions()?;
+
+        Ok(batch)
     }
 
     pub fn id(&self) -> BatchId {
@@ -57,6 +172,148 @@ impl Batch {
     pub fn bucket_depth(&self) -> u32 {
         self.bucket_depth
     }
+
+    pub fn immutable(&self) -> bool {
+        self.immutable
+    }
+
+    /// The number of chunks the batch can hold, `2^depth`, or `None` if a malformed depth is too
+    /// large to count in a `u64`.
+    pub fn chunks(&self) -> Option<u64> {
+        1u64.checked_shl(self.depth)
+    }
+
+    /// The number of bytes the batch can hold, or `None` if it is too large to count in a `u64`.
+    pub fn size(&self) -> Option<u64> {
+        self.chunks()?.checked_mul(CHUNK_SIZE)
+    }
+
+    /// The total cost of the batch when paying `amount` per chunk, or `None` on overflow.
+    pub fn cost(&self, amount: u128) -> Option<u128> {
+        (self.chunks()? as u128).checked_mul(amount)
+    }
+
+    /// The number of chunks each collision bucket can hold, `2^(depth - bucket_depth)`. A batch
+    /// with a bucket depth greater than its depth has no valid bucket size.
+    pub fn max_collisions(&self) -> Result<u64, BatchError> {
+        self.depth
+            .checked_sub(self.bucket_depth)
+            .and_then(|delta| 1u64.checked_shl(delta))
+            .ok_or(BatchError::InvalidBucketDepth(
+                self.depth,
+                self.bucket_depth,
+            ))
+    }
+
+    /// Whether the batch is still honoured by the network at `network_storage_depth`. Once the
+    /// storage depth rises above the bucket depth, the batch's buckets no longer map onto
+    /// neighbourhoods and its chunks stop being stored.
+    pub fn is_honored(&self, network_storage_depth: u32) -> bool {
+        self.bucket_depth >= network_storage_depth
+    }
+
+    /// The minimum depth of a batch that can hold `size` bytes, ie. `ceil(log2(chunks))` where
+    /// `chunks` is the number of chunks needed for `size` bytes, at least one.
+    pub fn depth_for_size(size: u64) -> u32 {
+        let chunks = size.div_ceil(CHUNK_SIZE).max(1);
+
+        chunks.next_power_of_two().trailing_zeros()
+    }
+
+    /// The cost of a batch holding `size` bytes for `duration` at the current `price` per chunk
+    /// per block: the batch's depth is the smallest holding `size`, and every chunk is paid
+    /// for the number of blocks in `duration`.
+    pub fn estimate_cost(size: u64, duration: Duration, price: u128) -> Result<u128, BatchError> {
+        let depth = Self::depth_for_size(size);
+        let amount = price
+            .checked_mul(duration.to_blocks() as u128)
+            .ok_or(BatchError::CostOverflow)?;
+
+        (1u128 << depth)
+            .checked_mul(amount)
+            .ok_or(BatchError::CostOverflow)
+    }
+
+    pub fn value(&self) -> u128 {
+        self.value
+    }
+
+    /// Add `additional` to the normalised balance of the batch, extending its time to live.
+    pub fn topup(&mut self, additional: u128) {
+        self.value = self.value.saturating_add(additional);
+    }
+
+    /// Increase the depth of the batch, doubling its capacity for every level added.
+    ///
+    /// The balance remaining above the postage contract's cumulative payout (`total_amount`) is
+    /// spread over the additional chunks, so the time to live halves for every level added. A
+    /// batch that has already expired, see `InMemoryBatchStore::cleanup_expired`, has no balance
+    /// left to spread and is refused with `BatchError::Expired`.
+    pub fn dilute(&mut self, new_depth: u8, total_amount: u128) -> Result<(), BatchError> {
+        let new_depth = u32::from(new_depth);
+        if new_depth <= self.depth {
+            return Err(BatchError::InvalidDilution(self.depth, new_depth));
+        }
+        if self.bucket_depth > new_depth {
+            return Err(BatchError::InvalidBucketDepth(new_depth, self.bucket_depth));
+        }
+        if self.value <= total_amount {
+            return Err(BatchError::Expired(self.id));
+        }
+
+        let remaining = self.value - total_amount;
+        self.value = total_amount + remaining.checked_shr(new_depth - self.depth).unwrap_or(0);
+        self.depth = new_depth;
+
+        Ok(())
+    }
+
+    /// The number of blocks until the batch expires, given the postage contract's current
+    /// cumulative payout per chunk (`total_amount`) and price per chunk per block.
+    ///
+    /// At a price of zero nothing is paid out, so the batch never expires and the time to live
+    /// is `u64::MAX`, as is any time to live too long to count in blocks.
+    pub fn ttl_blocks(&self, total_amount: u128, price: u128) -> u64 {
+        self.value
+            .saturating_sub(total_amount)
+            .checked_div(price)
+            .map_or(u64::MAX, |ttl| u64::try_from(ttl).unwrap_or(u64::MAX))
+    }
+
+    /// The block number at which the batch expires, counting from `current_block`.
+    pub fn expiry_block_number(&self, current_block: u64, total_amount: u128, price: u128) -> u64 {
+        current_block.saturating_add(self.ttl_blocks(total_amount, price))
+    }
+}
+
+/// The state written by `InMemoryBatchStore::save`
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    batches: Vec<Batch>,
+    pats: Vec<Pat>,
+}
+
+/// An error involving a batch
+#[derive(Debug, Error)]
+pub enum BatchError {
+    /// A dilution must increase the depth of the batch
+    #[error("invalid dilution from depth {0} to {1}")]
+    InvalidDilution(u32, u32),
+    /// A balance too large to be held as a batch value
+    #[error("value overflow: {0}")]
+    ValueOverflow(U256),
+    /// The bucket depth of a batch must not exceed its depth
+    #[error("invalid bucket depth {1} for depth {0}")]
+    InvalidBucketDepth(u32, u32),
+    /// A cost too large to be held as a `u128`
+    #[error("cost overflow")]
+    CostOverflow,
+    /// A batch id that is not 32 bytes of hex
+    #[error("invalid batch id: {0}")]
+    InvalidBatchId(String),
+    /// A batch whose value has been used up can no longer be diluted
+    #[error("batch expired")]
+    Expired(BatchId),
 }
 
 /// An error involving the batch store
@@ -67,56 +324,945 @@ pub enum BatchStoreError {
     BatchNotFound(BatchId),
 }
 
-pub(crate) struct Store {
+/// A mutation of the batch store, as recorded in its event log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchEvent {
+    /// A new batch was added to the store
+    Created(BatchId),
+    /// An existing batch's value was increased
+    ToppedUp(BatchId),
+    /// An existing batch's depth was increased
+    Diluted(BatchId),
+    /// A batch was removed as it has expired
+    Expired(BatchId),
+    /// A batch was removed from the store
+    Removed(BatchId),
+}
+
+/// A bounded, append-only log of batch events. When full, the oldest events are dropped.
+struct EventLog {
+    events: VecDeque<BatchEvent>,
+    capacity: usize,
+}
+
+impl EventLog {
+    fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn record(&mut self, event: BatchEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// A store of postage batches, keyed by batch id
+pub trait BatchStore {
+    /// Get a batch by its id
+    fn get(&self, id: BatchId) -> Option<Batch>;
+
+    /// Insert a batch, replacing any existing batch with the same id
+    fn put(&self, batch: Batch);
+
+    /// Remove a batch, returning it if it existed
+    fn remove(&self, id: BatchId) -> Option<Batch>;
+
+    /// Iterate over a snapshot of all batches in the store
+    fn iter(&self) -> Box<dyn Iterator<Item = Batch> + '_>;
+}
+
+// the address of the chunk validated at each stamp index of a batch
+type UsedStamps = HashMap<u64, [u8; 32]>;
+
+/// A `BatchStore` held in memory, optionally recording an event log of its mutations.
+pub struct InMemoryBatchStore {
     pub batches: Arc<Mutex<HashMap<BatchId, Batch>>>,
+    events: Option<Arc<Mutex<EventLog>>>,
+    used: Arc<Mutex<HashMap<BatchId, UsedStamps>>>,
 }
 
-impl Store {
+impl Default for InMemoryBatchStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryBatchStore {
     pub fn new() -> Self {
         Self {
             batches: Arc::new(Mutex::new(HashMap::new())),
+            events: None,
+            used: Arc::default(),
+        }
+    }
+
+    /// Create an empty store that records up to `capacity` of the most recent batch events.
+    pub fn with_event_log(capacity: usize) -> Self {
+        Self {
+            batches: Arc::new(Mutex::new(HashMap::new())),
+            events: Some(Arc::new(Mutex::new(EventLog::new(capacity)))),
+            used: Arc::default(),
         }
     }
 
     pub fn load(batches: HashMap<BatchId, Batch>) -> Self {
         Self {
             batches: Arc::new(Mutex::new(batches)),
+            events: None,
+            used: Arc::default(),
         }
     }
 
-    pub fn get(&self, id: BatchId) -> Option<Batch> {
-        self.batches.lock().unwrap().get(&id).cloned()
+    /// Write all batches in the store as JSON, ordered by batch id, along with the bucket
+    /// counters and wraparounds of `pats`, so that a restored pat doesn't hand out positions
+    /// already used.
+    pub fn save(&self, pats: &[Pat], w: impl Write) -> serde_json::Result<()> {
+        let mut batches: Vec<Batch> = self.batches.lock().unwrap().values().cloned().collect();
+        batches.sort_by_key(|batch| batch.id);
+
+        let mut pats = pats.to_vec();
+        pats.sort_by_key(|pat| pat.batch_id());
+
+        serde_json::to_writer(w, &Snapshot { batches, pats })
     }
 
-    pub fn insert(&self, batch: Batch) {
-        self.batches.lock().unwrap().insert(batch.id, batch);
+    /// Restore a store and the pats written by `save`. Batches are restored as they were
+    /// saved, so batches that have since expired are still present until `expire` removes
+    /// them. Pats take their batch parameters from the restored batches and have no signer,
+    /// so they stamp with `stamp_with` or `stamp_sync`, or with `stamp` once rehydrated. Pats
+    /// whose batch wasn't saved could no longer stamp and are dropped.
+    pub fn restore(r: impl Read) -> serde_json::Result<(Self, Vec<Pat>)> {
+        let Snapshot { batches, pats } = serde_json::from_reader(r)?;
+        let batches: HashMap<BatchId, Batch> =
+            batches.into_iter().map(|batch| (batch.id, batch)).collect();
+
+        let pats = pats
+            .into_iter()
+            .filter_map(|mut pat| {
+                pat.apply_batch(batches.get(&pat.batch_id())?);
+                Some(pat)
+            })
+            .collect();
+
+        Ok((Self::load(batches), pats))
+    }
+
+    /// Remove an expired batch from the store, returning it if it existed.
+    pub fn expire(&self, id: BatchId) -> Option<Batch> {
+        let expired = self.batches.lock().unwrap().remove(&id);
+        if expired.is_some() {
+            self.forget(id);
+            self.record(BatchEvent::Expired(id));
+        }
+        expired
+    }
+
+    /// Remove every batch whose value has been used up by the postage contract's cumulative
+    /// payout `total_amount`, returning the number of batches removed. Expiry is inclusive: a
+    /// batch whose value equals `total_amount` has nothing left to pay for storage.
+    pub fn cleanup_expired(&self, total_amount: u128) -> usize {
+        let mut batches = self.batches.lock().unwrap();
+        let expired: Vec<BatchId> = batches
+            .values()
+            .filter(|batch| batch.value <= total_amount)
+            .map(|batch| batch.id)
+            .collect();
+
+        for id in &expired {
+            batches.remove(id);
+        }
+        drop(batches);
+
+        for id in &expired {
+            self.forget(*id);
+            self.record(BatchEvent::Expired(*id));
+        }
+
+        expired.len()
+    }
+
+    /// Remove every batch owned by `owner`, eg. when the owner's key is compromised, returning
+    /// the number of batches removed.
+    pub fn cleanup_by_owner(&self, owner: Address) -> usize {
+        let mut batches = self.batches.lock().unwrap();
+        let owned: Vec<BatchId> = batches
+            .values()
+            .filter(|batch| batch.owner == owner)
+            .map(|batch| batch.id)
+            .collect();
+
+        for id in &owned {
+            batches.remove(id);
+        }
+        drop(batches);
+
+        for id in &owned {
+            self.forget(*id);
+            self.record(BatchEvent::Removed(*id));
+        }
+
+        owned.len()
+    }
+
+    /// The batches that expire at or before block `cutoff`, with their expiry block numbers,
+    /// soonest first. Expiry is projected from `current_block` with the postage contract's
+    /// cumulative payout `total_amount` and current `price`, as in `Batch::expiry_block_number`.
+    pub fn expiring_before(
+        &self,
+        cutoff: u64,
+        current_block: u64,
+        total_amount: u128,
+        price: u128,
+    ) -> Vec<(BatchId, u64)> {
+        let mut expiring: Vec<(BatchId, u64)> = self
+            .batches
+            .lock()
+            .unwrap()
+            .values()
+            .map(|batch| {
+                let expiry = batch.expiry_block_number(current_block, total_amount, price);
+                (batch.id, expiry)
+            })
+            .filter(|(_, expiry)| *expiry <= cutoff)
+            .collect();
+        expiring.sort_by_key(|(id, expiry)| (*expiry, *id));
+
+        expiring
     }
 
     pub fn exists(&self, id: BatchId) -> bool {
         self.batches.lock().unwrap().contains_key(&id)
     }
+
+    /// Whether the batch `id` is known and has room for more stamps, ie. stamps at fewer
+    /// distinct indices have been validated against it than the `2^depth` chunks it can hold.
+    /// Stamps are counted from when the store was created, they aren't persisted.
+    pub fn is_valid(&self, id: BatchId) -> bool {
+        let batch = match self.get(id) {
+            Some(batch) => batch,
+            None => return false,
+        };
+        let used = self.used.lock().unwrap().get(&id).map_or(0, HashMap::len);
+
+        batch.chunks().is_none_or(|chunks| (used as u64) < chunks)
+    }
+
+    /// Take all events recorded so far, oldest first. Returns nothing if the store was created
+    /// without an event log.
+    pub fn drain_events(&self) -> Vec<BatchEvent> {
+        match &self.events {
+            Some(log) => log.lock().unwrap().events.drain(..).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn record(&self, event: BatchEvent) {
+        if let Some(log) = &self.events {
+            log.lock().unwrap().record(event);
+        }
+    }
+
+    // drop the stamps validated against a batch that has left the store
+    fn forget(&self, id: BatchId) {
+        self.used.lock().unwrap().remove(&id);
+    }
+}
+
+impl BatchStore for InMemoryBatchStore {
+    fn get(&self, id: BatchId) -> Option<Batch> {
+        self.batches.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Insert a batch, replacing any existing batch with the same id. Replacing a batch with a
+    /// greater value or depth is recorded as a top up or dilution respectively.
+    fn put(&self, batch: Batch) {
+        let id = batch.id;
+        let previous = self.batches.lock().unwrap().insert(id, batch.clone());
+
+        match previous {
+            None => self.record(BatchEvent::Created(id)),
+            Some(previous) => {
+                if batch.value > previous.value {
+                    self.record(BatchEvent::ToppedUp(id));
+                }
+                if batch.depth > previous.depth {
+                    self.record(BatchEvent::Diluted(id));
+                }
+            }
+        }
+    }
+
+    fn remove(&self, id: BatchId) -> Option<Batch> {
+        let removed = self.batches.lock().unwrap().remove(&id);
+        if removed.is_some() {
+            self.forget(id);
+            self.record(BatchEvent::Removed(id));
+        }
+        removed
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Batch> + '_> {
+        let batches: Vec<Batch> = self.batches.lock().unwrap().values().cloned().collect();
+        Box::new(batches.into_iter())
+    }
 }
 
-impl StampValidator for Store {
+impl StampValidator for InMemoryBatchStore {
+    /// Validate a stamp against its batch in the store. Once an immutable batch has no room for
+    /// more stamps, see `is_valid`, it only accepts the stamps of chunks it already validated.
     fn validate_stamp<'a>(&'a self) -> ValidateStamp<'a> {
-        let store = self.clone();
+        let store = self;
         Box::new(move |chunk: &mut Chunk, stamp: MarshalledStamp| {
-            let stamp = Stamp::from(stamp);
-            match store.get(stamp.batch()) {
-                Some(batch) => {
-                    match stamp.valid(chunk, batch.owner, batch.depth, batch.bucket_depth) {
-                        Ok(_valid) => {
-                            chunk.add_stamp(stamp.into());
-                            Ok(())
-                        }
-                        Err(e) => Err(e),
-                    }
-                }
+            let stamp = PostageStamp::from_bytes(&stamp)?;
+            let batch_id: BatchId = stamp.batch_id().into();
+            let batch = match store.get(batch_id) {
+                Some(batch) => batch,
                 None => {
-                    error!("Batch not found: {:?}", stamp.batch());
-                    Err(StampError::BatchNotFound(stamp.batch()))
+                    error!("Batch not found: {:?}", batch_id);
+                    return Err(StampError::BatchNotFound(batch_id));
                 }
+            };
+
+            let (index, address) = (stamp.index(), chunk.address());
+            let known = store
+                .used
+                .lock()
+                .unwrap()
+                .get(&batch.id)
+                .is_some_and(|used| used.get(&index) == Some(&address));
+            if batch.immutable && !known && !store.is_valid(batch.id) {
+                return Err(StampError::BatchFull(batch.id));
             }
+
+            // depths beyond a u8 have no valid bucket capacity, and are rejected as such
+            let depth = u8::try_from(batch.depth).unwrap_or(u8::MAX);
+            let bucket_depth = u8::try_from(batch.bucket_depth).unwrap_or(u8::MAX);
+            stamp.verify_index(address.into(), depth, bucket_depth)?;
+            stamp.verify_signature(address.into(), batch.owner)?;
+            store
+                .used
+                .lock()
+                .unwrap()
+                .entry(batch.id)
+                .or_default()
+                .insert(index, address);
+            chunk.add_stamp(stamp.to_bytes().to_vec());
+            Ok(())
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_batch_events() {
+        let store = InMemoryBatchStore::with_event_log(16);
+        let id = [1u8; 32];
+
+        let batch = Batch::new(id, 100, None, Address::ZERO, 20, 16, false).unwrap();
+        store.put(batch.clone());
+
+        let mut topped_up = batch;
+        topped_up.value = 200;
+        store.put(topped_up);
+
+        assert!(store.remove(id).is_some());
+        assert!(store.remove(id).is_none());
+
+        assert_eq!(
+            store.drain_events(),
+            vec![
+                BatchEvent::Created(id),
+                BatchEvent::ToppedUp(id),
+                BatchEvent::Removed(id)
+            ]
+        );
+        assert!(store.drain_events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn validates_through_async_validator() {
+        use crate::{pat::Pat, stamp::AsyncStampValidator};
+        use alloy::signers::local::PrivateKeySigner;
+        use file::{chunk::Options, file::ChunkedFile};
+
+        let wallet = "be52c649a4c560a1012daa572d4e81627bcce20ca14e007aef87808a7fadd3d0"
+            .parse::<PrivateKeySigner>()
+            .unwrap();
+        let batch = Batch::new([1u8; 32], 0, None, wallet.address(), 18, 16, false).unwrap();
+
+        let chunk =
+            ChunkedFile::new(b"hello wordl".to_vec(), Options::default()).leaf_chunks()[0].clone();
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+        let stamped = pat.stamp_sync(chunk.clone(), Some(0), &wallet).unwrap();
+        let stamp: MarshalledStamp = stamped.stamp().unwrap().try_into().unwrap();
+
+        // the batch isn't known to the store yet
+        let store = InMemoryBatchStore::new();
+        let mut unstamped = chunk.clone();
+        assert!(matches!(
+            store.validate(&mut unstamped, stamp).await,
+            Err(StampError::BatchNotFound(id)) if id == [1u8; 32]
+        ));
+
+        store.put(batch);
+        let mut unstamped = chunk;
+        store.validate(&mut unstamped, stamp).await.unwrap();
+        assert_eq!(unstamped.stamp(), stamped.stamp());
+    }
+
+    #[tokio::test]
+    async fn exhausted_batch_rejects_new_stamps() {
+        use crate::{pat::Pat, stamp::AsyncStampValidator};
+        use alloy::signers::local::PrivateKeySigner;
+        use file::{chunk::Options, file::ChunkedFile};
+
+        let wallet = "be52c649a4c560a1012daa572d4e81627bcce20ca14e007aef87808a7fadd3d0"
+            .parse::<PrivateKeySigner>()
+            .unwrap();
+        // room for 2 chunks, one in each bucket
+        let batch = Batch::new([1u8; 32], 0, None, wallet.address(), 1, 1, true).unwrap();
+        let store = InMemoryBatchStore::new();
+        store.put(batch.clone());
+
+        // a chunk falling into each bucket, then another one into the first
+        let mut found = [0, 0];
+        let mut chunks: Vec<Chunk> = (0..)
+            .map(|i| {
+                ChunkedFile::new(format!("{i}").into_bytes(), Options::default()).leaf_chunks()[0]
+                    .clone()
+            })
+            .filter(|chunk| {
+                let bucket = (chunk.address()[0] >> 7) as usize;
+                found[bucket] += 1;
+                found[bucket] <= 2 - bucket
+            })
+            .take(3)
+            .collect();
+        if chunks[1].address()[0] >> 7 == 0 {
+            chunks.swap(1, 2);
+        }
+
+        // the last chunk is stamped by another issuer unaware of the first stamps
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+        let mut stamps: Vec<MarshalledStamp> = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            if i == 2 {
+                pat = Pat::new(&batch, 0, false, wallet.clone());
+            }
+            let stamped = pat.stamp_sync(chunk.clone(), Some(0), &wallet).unwrap();
+            stamps.push(stamped.stamp().unwrap().try_into().unwrap());
+        }
+
+        assert!(store.is_valid(batch.id));
+        store
+            .validate(&mut chunks[0].clone(), stamps[0])
+            .await
+            .unwrap();
+        assert!(store.is_valid(batch.id));
+        store
+            .validate(&mut chunks[1].clone(), stamps[1])
+            .await
+            .unwrap();
+        assert!(!store.is_valid(batch.id));
+
+        // the chunks already stamped are still accepted, but not a new one
+        store
+            .validate(&mut chunks[0].clone(), stamps[0])
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.validate(&mut chunks[2].clone(), stamps[2]).await,
+            Err(StampError::BatchFull(id)) if id == batch.id
+        ));
+
+        // the count goes with the batch
+        store.remove(batch.id);
+        assert!(!store.is_valid(batch.id));
+        store.put(batch.clone());
+        assert!(store.is_valid(batch.id));
+    }
+
+    #[test]
+    fn honored_until_storage_depth_exceeds_bucket_depth() {
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 20, 16, false).unwrap();
+
+        assert!(batch.is_honored(0));
+        assert!(batch.is_honored(15));
+        assert!(batch.is_honored(16));
+        assert!(!batch.is_honored(17));
+    }
+
+    #[test]
+    fn depth_for_size() {
+        assert_eq!(Batch::depth_for_size(1), 0);
+        assert_eq!(Batch::depth_for_size(CHUNK_SIZE), 0);
+        assert_eq!(Batch::depth_for_size(CHUNK_SIZE + 1), 1);
+        assert_eq!(Batch::depth_for_size(128 * CHUNK_SIZE), 7);
+        assert_eq!(Batch::depth_for_size(128 * CHUNK_SIZE + 1), 8);
+    }
+
+    #[test]
+    fn depth_for_size_below_chunk_size() {
+        // anything up to a single chunk needs depth 0, including an empty payload
+        for size in [0, 1, CHUNK_SIZE / 2, CHUNK_SIZE - 1, CHUNK_SIZE] {
+            assert_eq!(Batch::depth_for_size(size), 0);
+        }
+    }
+
+    #[test]
+    fn topup_extends_ttl() {
+        let mut batch = Batch::new([1u8; 32], 1_100, None, Address::ZERO, 20, 16, false).unwrap();
+
+        // 100 per chunk remaining at a price of 10 per block
+        assert_eq!(batch.ttl_blocks(1_000, 10), 10);
+        assert_eq!(batch.expiry_block_number(50, 1_000, 10), 60);
+
+        batch.topup(100);
+
+        assert_eq!(batch.value(), 1_200);
+        assert_eq!(batch.ttl_blocks(1_000, 10), 20);
+        assert_eq!(batch.expiry_block_number(50, 1_000, 10), 70);
+    }
+
+    #[test]
+    fn capacity_at_large_depths() {
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 20, 16, false).unwrap();
+        assert_eq!(batch.chunks(), Some(1 << 20));
+        assert_eq!(batch.size(), Some((1 << 20) * CHUNK_SIZE));
+        assert_eq!(batch.cost(10), Some(10 << 20));
+
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 63, 16, false).unwrap();
+        assert_eq!(batch.chunks(), Some(1 << 63));
+        assert_eq!(batch.size(), None);
+        assert_eq!(batch.cost(2), Some(1 << 64));
+        assert_eq!(batch.max_collisions().unwrap(), 1 << 47);
+
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 64, 0, false).unwrap();
+        assert_eq!(batch.chunks(), None);
+        assert_eq!(batch.size(), None);
+        assert_eq!(batch.cost(1), None);
+        assert!(batch.max_collisions().is_err());
+    }
+
+    #[test]
+    fn duration_rounds_up_to_whole_blocks() {
+        let time = |secs| Duration::Time {
+            secs,
+            block_time: 15,
+        };
+
+        assert_eq!(time(3600).to_blocks(), 240);
+        assert_eq!(time(3601).to_blocks(), 241);
+        assert_eq!(time(14).to_blocks(), 1);
+        assert_eq!(time(0).to_blocks(), 0);
+        assert_eq!(Duration::Blocks(7).to_blocks(), 7);
+    }
+
+    #[test]
+    fn estimate_cost() {
+        // 1 MiB for an hour with 15 second blocks
+        let size = 1024 * 1024;
+        let duration = Duration::Time {
+            secs: 3600,
+            block_time: 15,
+        };
+        let price = 24_000;
+
+        let depth = Batch::depth_for_size(size);
+        assert_eq!(depth, 8);
+        let amount = price * duration.to_blocks() as u128;
+        let batch = Batch::new([1u8; 32], amount, None, Address::ZERO, depth, 0, false).unwrap();
+
+        assert_eq!(
+            Batch::estimate_cost(size, duration, price).unwrap(),
+            batch.cost(amount).unwrap()
+        );
+        assert_eq!(
+            Batch::estimate_cost(size, duration, price).unwrap(),
+            256 * 240 * 24_000
+        );
+
+        assert!(matches!(
+            Batch::estimate_cost(u64::MAX, Duration::Blocks(u64::MAX), u128::MAX),
+            Err(BatchError::CostOverflow)
+        ));
+    }
+
+    #[test]
+    fn zero_price_never_expires() {
+        let batch = Batch::new([1u8; 32], 1_100, None, Address::ZERO, 20, 16, false).unwrap();
+
+        assert_eq!(batch.ttl_blocks(1_000, 0), u64::MAX);
+        assert_eq!(batch.expiry_block_number(50, 1_000, 0), u64::MAX);
+
+        let store = InMemoryBatchStore::new();
+        store.put(batch);
+        assert!(store.expiring_before(u64::MAX - 1, 50, 1_000, 0).is_empty());
+
+        // a remaining balance too large to count in blocks saturates as well
+        let batch = Batch::new([1u8; 32], u128::MAX, None, Address::ZERO, 20, 16, false).unwrap();
+        assert_eq!(batch.ttl_blocks(0, 1), u64::MAX);
+    }
+
+    #[test]
+    fn dilute_increases_depth_and_reduces_ttl() {
+        let mut batch = Batch::new([1u8; 32], 1_400, None, Address::ZERO, 20, 16, false).unwrap();
+        assert_eq!(batch.ttl_blocks(1_000, 10), 40);
+
+        batch.dilute(22, 1_000).unwrap();
+
+        assert_eq!(batch.depth(), 22);
+        assert!(batch.bucket_depth() <= batch.depth());
+        assert_eq!(batch.ttl_blocks(1_000, 10), 10);
+
+        assert!(matches!(
+            batch.dilute(22, 1_000),
+            Err(BatchError::InvalidDilution(22, 22))
+        ));
+        assert!(batch.dilute(21, 1_000).is_err());
+        assert_eq!(batch.depth(), 22);
+
+        // the bucket depth still can't exceed the diluted depth
+        batch.bucket_depth = 30;
+        assert!(matches!(
+            batch.dilute(24, 1_000),
+            Err(BatchError::InvalidBucketDepth(24, 30))
+        ));
+        assert_eq!(batch.depth(), 22);
+
+        // the whole balance is paid out when diluting by more levels than it has bits
+        batch.bucket_depth = 16;
+        batch.dilute(u8::MAX, 1_000).unwrap();
+        assert_eq!(batch.value(), 1_000);
+
+        // an expired batch is refused rather than having its value raised to the payout
+        let mut batch = Batch::new([1u8; 32], 900, None, Address::ZERO, 20, 16, false).unwrap();
+        for total_amount in [900, 1_000] {
+            assert!(matches!(
+                batch.dilute(22, total_amount),
+                Err(BatchError::Expired(id)) if id == [1u8; 32]
+            ));
+            assert_eq!((batch.value(), batch.depth()), (900, 20));
+        }
+    }
+
+    #[test]
+    fn batch_from_event() {
+        let event = BatchCreated {
+            batch_id: [7u8; 32],
+            total_amount: U256::from(1_000_000_000u64) << 20,
+            normalised_balance: U256::from(1_100_000_000u64),
+            owner: Address::repeat_byte(0x11),
+            depth: 20,
+            bucket_depth: 16,
+            immutable: true,
+        };
+
+        let batch = Batch::from_event(&event, 1234).unwrap();
+
+        assert_eq!(batch.id(), [7u8; 32]);
+        assert_eq!(batch.value(), 1_100_000_000);
+        assert_eq!(batch.block_created, Some(1234));
+        assert_eq!(batch.owner(), Address::repeat_byte(0x11));
+        assert_eq!(batch.depth(), 20);
+        assert_eq!(batch.bucket_depth(), 16);
+        assert!(batch.immutable());
+
+        let overflow = BatchCreated {
+            normalised_balance: U256::MAX,
+            ..event
+        };
+        assert!(matches!(
+            Batch::from_event(&overflow, 1234),
+            Err(BatchError::ValueOverflow(_))
+        ));
+    }
+
+    #[test]
+    fn max_collisions() {
+        let batch = Batch::new([0u8; 32], 0, None, Address::ZERO, 20, 16, false).unwrap();
+        assert_eq!(batch.max_collisions().unwrap(), 16);
+
+        let batch = Batch::new([0u8; 32], 0, None, Address::ZERO, 16, 16, false).unwrap();
+        assert_eq!(batch.max_collisions().unwrap(), 1);
+
+        // 2^(16 - 17) would underflow, so the batch can't be created
+        assert!(matches!(
+            Batch::new([0u8; 32], 0, None, Address::ZERO, 16, 17, false),
+            Err(BatchError::InvalidBucketDepth(16, 17))
+        ));
+
+        // a bucket depth raised above the depth afterwards is still caught
+        let mut batch = Batch::new([0u8; 32], 0, None, Address::ZERO, 16, 16, false).unwrap();
+        batch.bucket_depth = 17;
+        assert!(matches!(
+            batch.max_collisions(),
+            Err(BatchError::InvalidBucketDepth(16, 17))
+        ));
+
+        let event = BatchCreated {
+            batch_id: [0u8; 32],
+            total_amount: U256::ZERO,
+            normalised_balance: U256::ZERO,
+            owner: Address::ZERO,
+            depth: 16,
+            bucket_depth: 17,
+            immutable: false,
+        };
+        assert!(matches!(
+            Batch::from_event(&event, 0),
+            Err(BatchError::InvalidBucketDepth(16, 17))
+        ));
+    }
+
+    #[test]
+    fn batch_from_bee_json() {
+        // response of bee's `/stamps/{id}` endpoint
+        let json = r#"{
+            "batchID": "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369",
+            "utilization": 2,
+            "usable": true,
+            "label": "",
+            "depth": 20,
+            "amount": "113314620000000000000",
+            "bucketDepth": 16,
+            "blockNumber": 30643611,
+            "immutableFlag": true,
+            "exists": true,
+            "batchTTL": 4771392,
+            "expired": false
+        }"#;
+
+        let batch: Batch = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            hex::encode(batch.id()),
+            "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369"
+        );
+        assert_eq!(batch.value(), 113_314_620_000_000_000_000);
+        assert_eq!(batch.block_created, Some(30643611));
+        assert_eq!(batch.depth(), 20);
+        assert_eq!(batch.bucket_depth(), 16);
+        assert!(batch.immutable);
+        assert_eq!(batch.owner(), Address::ZERO);
+
+        let value = serde_json::to_value(&batch).unwrap();
+        assert_eq!(
+            value["batchID"],
+            "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369"
+        );
+        assert_eq!(value["amount"], "113314620000000000000");
+        assert_eq!(value["bucketDepth"], 16);
+        assert_eq!(value["immutableFlag"], true);
+    }
+
+    #[test]
+    fn in_memory_batch_store() {
+        let store = InMemoryBatchStore::new();
+
+        for i in 0..3u8 {
+            store.put(Batch::new([i; 32], 0, None, Address::ZERO, 20, 16, false).unwrap());
+        }
+
+        assert!(store.get([1; 32]).is_some());
+        assert!(store.get([3; 32]).is_none());
+
+        let mut ids: Vec<BatchId> = store.iter().map(|b| b.id()).collect();
+        ids.sort();
+        assert_eq!(ids, vec![[0; 32], [1; 32], [2; 32]]);
+
+        assert!(store.remove([1; 32]).is_some());
+        assert!(store.get([1; 32]).is_none());
+        assert_eq!(store.iter().count(), 2);
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let store = InMemoryBatchStore::new();
+        let owner = Address::repeat_byte(0x11);
+
+        for i in 0..3u8 {
+            store.put(
+                Batch::new(
+                    [i; 32],
+                    1_000 + i as u128,
+                    Some(i as u64),
+                    owner,
+                    20,
+                    16,
+                    i == 1,
+                )
+                .unwrap(),
+            );
+        }
+
+        let mut saved = Vec::new();
+        store.save(&[], &mut saved).unwrap();
+
+        let (restored, pats) = InMemoryBatchStore::restore(saved.as_slice()).unwrap();
+        assert!(pats.is_empty());
+        assert_eq!(restored.iter().count(), 3);
+        for i in 0..3u8 {
+            let batch = restored.get([i; 32]).unwrap();
+            assert_eq!(batch.value(), 1_000 + i as u128);
+            assert_eq!(batch.block_created, Some(i as u64));
+            assert_eq!(batch.owner(), owner);
+            assert_eq!(batch.depth(), 20);
+            assert_eq!(batch.bucket_depth(), 16);
+            assert_eq!(batch.immutable(), i == 1);
+        }
+
+        // an expired batch is only dropped once expired from the restored store
+        assert!(restored.expire([0; 32]).is_some());
+        assert_eq!(restored.iter().count(), 2);
+    }
+
+    #[test]
+    fn save_and_restore_pats() {
+        use crate::pat::Pat;
+        use alloy::signers::local::PrivateKeySigner;
+        use file::{chunk::Options, file::ChunkedFile};
+
+        let wallet = "be52c649a4c560a1012daa572d4e81627bcce20ca14e007aef87808a7fadd3d0"
+            .parse::<PrivateKeySigner>()
+            .unwrap();
+        let store = InMemoryBatchStore::new();
+        let mutable = Batch::new([1; 32], 0, None, wallet.address(), 18, 16, false).unwrap();
+        let gone = Batch::new([2; 32], 0, None, wallet.address(), 18, 16, false).unwrap();
+        store.put(mutable.clone());
+
+        // fill the chunk's bucket of 2^(18 - 16) positions and wrap around once
+        let chunk =
+            ChunkedFile::new(b"hello wordl".to_vec(), Options::default()).leaf_chunks()[0].clone();
+        let mut pat = Pat::new(&mutable, 0, false, wallet.clone());
+        for _ in 0..5 {
+            pat.inc(&chunk).unwrap();
+        }
+
+        let mut saved = Vec::new();
+        let pats = [pat.clone(), Pat::new(&gone, 0, false, wallet.clone())];
+        store.save(&pats, &mut saved).unwrap();
+
+        // the pat of the batch that wasn't saved is dropped
+        let (restored, mut pats) = InMemoryBatchStore::restore(saved.as_slice()).unwrap();
+        assert_eq!(pats.len(), 1);
+        assert!(restored.exists([1; 32]));
+
+        let mut restored_pat = pats.remove(0);
+        assert_eq!(restored_pat.wrapped_buckets(), pat.wrapped_buckets());
+        assert_eq!(restored_pat.bucket_stats(), pat.bucket_stats());
+        assert_eq!(restored_pat.bucket_upper_bound(), 4);
+        assert_eq!(restored_pat.inc(&chunk).unwrap(), pat.inc(&chunk).unwrap());
+
+        // and stamps with the restored counters
+        let stamped = restored_pat.stamp_sync(chunk, Some(0), &wallet).unwrap();
+        assert!(stamped.stamp().is_some());
+    }
+
+    #[test]
+    fn cleanup_expired() {
+        let store = InMemoryBatchStore::with_event_log(8);
+        store.put(Batch::new([1; 32], 100, None, Address::ZERO, 20, 16, false).unwrap());
+        store.put(Batch::new([2; 32], 200, None, Address::ZERO, 20, 16, false).unwrap());
+        store.drain_events();
+
+        assert_eq!(store.cleanup_expired(50), 0);
+        assert_eq!(store.iter().count(), 2);
+
+        // a batch is expired once the payout reaches its value
+        assert_eq!(store.cleanup_expired(100), 1);
+        assert!(!store.exists([1; 32]));
+        assert!(store.exists([2; 32]));
+        assert_eq!(store.drain_events(), vec![BatchEvent::Expired([1; 32])]);
+
+        assert_eq!(store.cleanup_expired(150), 0);
+        assert_eq!(store.cleanup_expired(250), 1);
+        assert_eq!(store.iter().count(), 0);
+    }
+
+    #[test]
+    fn parse_batch_id_hex() {
+        let id = "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369";
+        let batch_id = parse_batch_id(id).unwrap();
+
+        assert_eq!(batch_id[..4], [0xc3, 0x38, 0x78, 0x32]);
+        assert_eq!(parse_batch_id(&format!("0x{id}")).unwrap(), batch_id);
+        assert_eq!(hex::encode(batch_id), id);
+
+        assert!(matches!(
+            parse_batch_id(&id[..62]),
+            Err(BatchError::InvalidBatchId(_))
+        ));
+        assert!(parse_batch_id("not hex").is_err());
+    }
+
+    #[test]
+    fn cleanup_by_owner() {
+        let store = InMemoryBatchStore::with_event_log(8);
+        let (alice, bob) = (Address::from([1; 20]), Address::from([2; 20]));
+        for (id, owner) in [([1; 32], alice), ([2; 32], bob), ([3; 32], alice)] {
+            store.put(Batch::new(id, 100, None, owner, 20, 16, false).unwrap());
+        }
+        store.drain_events();
+
+        assert_eq!(store.cleanup_by_owner(alice), 2);
+        assert_eq!(
+            store.iter().map(|batch| batch.id).collect::<Vec<_>>(),
+            vec![[2; 32]]
+        );
+
+        // removed in map order
+        let events = store.drain_events();
+        assert_eq!(events.len(), 2);
+        assert!(events.contains(&BatchEvent::Removed([1; 32])));
+        assert!(events.contains(&BatchEvent::Removed([3; 32])));
+
+        assert_eq!(store.cleanup_by_owner(alice), 0);
+        assert!(store.exists([2; 32]));
+    }
+
+    #[test]
+    fn expiring_before() {
+        let store = InMemoryBatchStore::new();
+
+        // at a price of 10 per block, these expire 30, 10 and 20 blocks from now
+        store.put(Batch::new([1; 32], 300, None, Address::ZERO, 20, 16, false).unwrap());
+        store.put(Batch::new([2; 32], 100, None, Address::ZERO, 20, 16, false).unwrap());
+        store.put(Batch::new([3; 32], 200, None, Address::ZERO, 20, 16, false).unwrap());
+
+        assert_eq!(
+            store.expiring_before(1_020, 1_000, 0, 10),
+            vec![([2; 32], 1_010), ([3; 32], 1_020)]
+        );
+        assert!(store.expiring_before(1_009, 1_000, 0, 10).is_empty());
+
+        // nothing is removed from the store
+        assert_eq!(store.iter().count(), 3);
+    }
+
+    #[test]
+    fn event_log_is_bounded() {
+        let store = InMemoryBatchStore::with_event_log(2);
+
+        for i in 0..3u8 {
+            store.put(Batch::new([i; 32], 0, None, Address::ZERO, 20, 16, false).unwrap());
+        }
+
+        assert_eq!(
+            store.drain_events(),
+            vec![BatchEvent::Created([1; 32]), BatchEvent::Created([2; 32])]
+        );
+    }
+}
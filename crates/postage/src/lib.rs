@@ -1,6 +1,4 @@
-#![feature(async_closure)]
-#![feature(associated_type_bounds)]
-
 pub mod batch;
+pub mod clock;
 pub mod pat;
 pub mod stamp;
@@ -1,15 +1,19 @@
 use serde::{Deserialize, Serialize};
-use std::{borrow::BorrowMut, future::Future, pin::Pin};
+use std::{borrow::BorrowMut, sync::Arc};
 use thiserror::Error;
-use tracing::error;
 // use serde_json::Result;
-use ethers_signers::{LocalWallet, Signer};
+use alloy::{
+    primitives::{Address, PrimitiveSignature},
+    signers::{local::PrivateKeySigner, Signer, SignerSync},
+};
 
 use crate::{
-    batch::{Batch, BatchId, Store},
-    stamp::Stamp,
+    batch::{Batch, BatchId, BatchStore},
+    clock::{Clock, SystemClock},
+    stamp::PostageStamp,
 };
-use bmt::chunk::Chunk;
+use file::chunk::Chunk;
+use nectar_primitives::postage::digest;
 
 /// An error involving Postman Pat 📬
 #[derive(Debug, Error)]
@@ -20,137 +24,341 @@ pub enum PatError {
     /// When a batch isn't found in the store
     #[error("batch not found")]
     BatchNotFound(BatchId),
+    /// When stamping with a batch that has expired
+    #[error("batch expired")]
+    BatchExpired(BatchId),
+    /// When stamping with a signer that doesn't sign for the batch owner
+    #[error("owner mismatch, expected {0}, got {1}")]
+    OwnerMismatch(Address, Address),
+    /// When the signer fails to sign a stamp
+    #[error("signer error: {0}")]
+    Signer(String),
+    /// When stamping with the pat's own signer, but it has none, eg. once restored
+    #[error("no signer")]
+    NoSigner,
+}
+
+/// A stamp that has been assigned its bucket position and timestamp, awaiting a signature
+/// over `digest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StampRequest {
+    pub digest: [u8; 32], // the digest to be signed
+    pub x: u32,           // which collision bucket the chunk is in
+    pub y: u32,           // where in bucket the chunk is
+    pub timestamp: u64,   // timestamp of the stamp
+}
+
+/// Occupancy of the collision buckets of a batch, for deciding when to dilute it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketStats {
+    pub min: u32,    // the fill of the emptiest bucket
+    pub max: u32,    // the fill of the fullest bucket
+    pub mean: f64,   // the mean fill across all buckets
+    pub full: usize, // how many buckets are full
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pat {
     batch_id: BatchId,  // the batch id
     batch_amount: u128, // the amount paid for the batch
-    #[serde(skip_serializing)]
+    #[serde(skip)]
     batch_depth: u32, // batch depth: batch size = 2^{batch_depth}
-    #[serde(skip_serializing)]
+    #[serde(skip)]
     batch_bucket_depth: u32, // bucket depth: the depth of collision buckets uniformity
     buckets: Vec<u32>, // Collision buckets: counts per neighbourhoods (limited to 2^{batchDepth-bucketDepth})
     max_bucket_depth: u32, // the depth of the fullest bucket
-    #[serde(skip_serializing)]
+    #[serde(default)]
+    wraps: Vec<u32>, // Wraparounds: how many times each bucket of a mutable batch was overwritten
+    #[serde(skip)]
     block_created: Option<u64>, // the block number when this batch was created
-    #[serde(skip_serializing)]
+    #[serde(skip)]
+    owner: Address, // the batch owner, whose key every stamp must be signed with
+    #[serde(skip)]
     immutable: bool, // whether the batch is immutable
-    #[serde(skip_serializing)]
+    #[serde(default)]
     expired: bool, // whether the batch is expired
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
-    signer: Option<LocalWallet>, // the signer
+    signer: Option<PrivateKeySigner>, // the signer
+    #[serde(skip, default = "system_clock")]
+    clock: Arc<dyn Clock>, // the clock timestamping stamps
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
 }
 
 impl Pat {
-    pub fn new(batch: &Batch, batch_amount: u128, expired: bool, signer: LocalWallet) -> Self {
+    pub fn new(batch: &Batch, batch_amount: u128, expired: bool, signer: PrivateKeySigner) -> Self {
         Self {
             batch_id: batch.id,
             batch_amount,
             batch_depth: batch.depth,
             batch_bucket_depth: batch.bucket_depth,
-            buckets: vec![0; 2usize.pow(batch.bucket_depth as u32)],
+            buckets: vec![0; 2usize.pow(batch.bucket_depth)],
             max_bucket_depth: 0,
+            wraps: vec![0; 2usize.pow(batch.bucket_depth)],
             block_created: batch.block_created,
+            owner: batch.owner,
             immutable: batch.immutable,
             expired,
             signer: Some(signer),
+            clock: system_clock(),
         }
     }
 
+    /// Use `clock` for the timestamps of stamps that aren't given one, in place of the system
+    /// clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     pub fn inc(&mut self, chunk: &Chunk) -> std::result::Result<(u32, u32), PatError> {
+        // stamps from an expired batch would never validate
+        if self.expired {
+            return Err(PatError::BatchExpired(self.batch_id));
+        }
+
         // get which bucket the chunk belongs to
         let x = chunk.get_x(self.batch_bucket_depth);
         let upper_bound = self.bucket_upper_bound();
         let count = self.buckets[x as usize].borrow_mut();
-        let idx = *count;
 
         // check if the bucket is full
-        match *count == upper_bound {
-            true => {
-                // check if immutable
-                if self.immutable {
-                    return Err(PatError::BucketFull());
-                }
-
-                *count = 0;
-            }
-            false => {
-                // increment the bucket
-                *count += 1;
-                if *count > self.max_bucket_depth {
-                    self.max_bucket_depth = *count;
-                }
+        if *count == upper_bound {
+            // check if immutable
+            if self.immutable {
+                return Err(PatError::BucketFull());
             }
+
+            // mutable batches overwrite the bucket from the start
+            *count = 0;
+            self.wraps[x as usize] += 1;
+        }
+
+        // the next free index in the bucket, 0..upper_bound
+        let idx = *count;
+
+        // increment the bucket
+        *count += 1;
+        if *count > self.max_bucket_depth {
+            self.max_bucket_depth = *count;
         }
 
         Ok((x, idx))
     }
 
-    pub async fn stamp<'a>(
-        &'a mut self,
+    /// Stamp a chunk, signing with the pat's own signer. Pats restored from a snapshot have no
+    /// signer until they are rehydrated, and are refused with `PatError::NoSigner`.
+    pub async fn stamp(
+        &mut self,
+        chunk: Chunk,
+        timestamp: Option<u64>,
+    ) -> std::result::Result<Chunk, PatError> {
+        let signer = self.signer.clone().ok_or(PatError::NoSigner)?;
+        self.stamp_with(chunk, timestamp, &signer).await
+    }
+
+    /// Stamp a chunk, signing with `signer` rather than the pat's own signer, eg. for a team
+    /// sharing a batch where each member stamps with their own copy of the owner key. Bucket
+    /// accounting is shared by every signer stamping through this pat.
+    ///
+    /// A stamp only validates if its signature recovers to the batch owner, so a signer for
+    /// any other address is refused with `PatError::OwnerMismatch` before a bucket position is
+    /// used up.
+    pub async fn stamp_with<S: Signer + Sync>(
+        &mut self,
         mut chunk: Chunk,
         timestamp: Option<u64>,
+        signer: &S,
     ) -> std::result::Result<Chunk, PatError> {
-        let (x, y) = self.inc(&chunk)?;
+        self.check_signer(signer.address())?;
+        let request = self.prepare(&chunk, timestamp)?;
 
-        let timestamp = timestamp.unwrap_or_else(|| chrono::Utc::now().timestamp_nanos() as u64);
+        let signature = signer
+            .sign_message(&request.digest)
+            .await
+            .map_err(|e| PatError::Signer(e.to_string()))?;
 
-        let signer = self.signer.as_ref().expect("Signer is not set");
+        let stamp = self.finalize(request, signature);
+        chunk.add_stamp(stamp.to_bytes().to_vec());
+        Ok(chunk)
+    }
 
-        let stamp = Stamp::new(
-            &chunk,
-            self.batch_id,
+    /// Stamp a chunk, signing synchronously with a local key. This avoids the boxed signing
+    /// future of `stamp`, which is only needed for remote signers.
+    pub fn stamp_sync(
+        &mut self,
+        mut chunk: Chunk,
+        timestamp: Option<u64>,
+        signer: &PrivateKeySigner,
+    ) -> std::result::Result<Chunk, PatError> {
+        self.check_signer(signer.address())?;
+        let request = self.prepare(&chunk, timestamp)?;
+
+        let signature = signer
+            .sign_message_sync(&request.digest)
+            .map_err(|e| PatError::Signer(e.to_string()))?;
+
+        let stamp = self.finalize(request, signature);
+        chunk.add_stamp(stamp.to_bytes().to_vec());
+        Ok(chunk)
+    }
+
+    /// First phase of stamping without a local signer: reserve the chunk's position in its
+    /// bucket and compute the digest to be signed. The signature is an EIP-191 personal
+    /// message signature over the digest, as produced by `Signer::sign_message`.
+    pub fn prepare(
+        &mut self,
+        chunk: &Chunk,
+        timestamp: Option<u64>,
+    ) -> std::result::Result<StampRequest, PatError> {
+        let (x, y) = self.inc(chunk)?;
+
+        let timestamp = timestamp.unwrap_or_else(|| self.clock.now_nanos());
+
+        Ok(StampRequest {
+            digest: digest(
+                chunk.address().into(),
+                self.batch_id.into(),
+                stamp_index(x, y),
+                timestamp,
+            )
+            .into(),
             x,
             y,
             timestamp,
-            Box::new(move |digest| {
-                let signer = signer.clone();
-                Box::pin(async move {
-                    let result = signer.sign_message(digest).await?;
-                    let result: [u8; 65] = result.to_vec().as_slice().try_into()?;
-                    Ok(result)
-                })
-                    as Pin<
-                        Box<dyn Future<Output = Result<[u8; 65], Box<dyn std::error::Error + 'a>>>>,
-                    >
-            }),
+        })
+    }
+
+    /// Second phase of stamping: build the stamp from a prepared request and its signature.
+    pub fn finalize(&self, request: StampRequest, signature: PrimitiveSignature) -> PostageStamp {
+        PostageStamp::new(
+            self.batch_id.into(),
+            stamp_index(request.x, request.y),
+            request.timestamp,
+            signature,
         )
-        .await;
+    }
 
-        chunk.add_stamp(stamp.into());
-        Ok(chunk)
+    /// The buckets of a mutable batch that have wrapped around, with the number of times each
+    /// has. Every wrap overwrites the bucket's earlier stamps, so chunks stamped in these
+    /// buckets before the wrap may no longer have a valid stamp.
+    pub fn wrapped_buckets(&self) -> Vec<(u32, u32)> {
+        self.wraps
+            .iter()
+            .enumerate()
+            .filter(|(_, wraps)| **wraps > 0)
+            .map(|(x, wraps)| (x as u32, *wraps))
+            .collect()
     }
 
     pub fn utilization(&self) -> u32 {
         self.max_bucket_depth
     }
 
+    /// The distribution of fill across the buckets, where `utilization` only reports the
+    /// fullest.
+    pub fn bucket_stats(&self) -> BucketStats {
+        let upper_bound = self.bucket_upper_bound();
+        let total: u64 = self.buckets.iter().map(|count| *count as u64).sum();
+
+        BucketStats {
+            min: self.buckets.iter().copied().min().unwrap_or(0),
+            max: self.buckets.iter().copied().max().unwrap_or(0),
+            mean: total as f64 / self.buckets.len() as f64,
+            full: self
+                .buckets
+                .iter()
+                .filter(|count| **count >= upper_bound)
+                .count(),
+        }
+    }
+
+    /// The number of stamps that can still be issued before every bucket is full.
+    pub fn remaining_capacity(&self) -> u64 {
+        let total = self.buckets.len() as u64 * self.bucket_upper_bound() as u64;
+        let used: u64 = self.buckets.iter().map(|count| *count as u64).sum();
+
+        total - used
+    }
+
+    /// Whether every bucket of the batch is full. This is a global check: a single full
+    /// bucket of an immutable batch already refuses chunks that fall into it with
+    /// `PatError::BucketFull`, while the other buckets can still be stamped.
+    pub fn is_full(&self) -> bool {
+        self.remaining_capacity() == 0
+    }
+
+    /// Whether `chunk` can be stamped, accounting for the occupancy of the bucket it falls into
+    /// rather than the capacity of the whole batch. Mutable batches can always stamp, as a
+    /// full bucket wraps around.
+    pub fn can_stamp(&self, chunk: &Chunk) -> bool {
+        if self.expired {
+            return false;
+        }
+
+        let x = chunk.get_x(self.batch_bucket_depth);
+        !self.immutable || self.buckets[x as usize] < self.bucket_upper_bound()
+    }
+
+    /// The number of stamps each bucket can hold, `2^(depth - bucket_depth)`, saturating at
+    /// `u32::MAX` as the bucket counters can't count any further.
     pub fn bucket_upper_bound(&self) -> u32 {
-        1 << (self.batch_depth - self.batch_bucket_depth)
+        let delta = self.batch_depth.saturating_sub(self.batch_bucket_depth);
+        1u32.checked_shl(delta).unwrap_or(u32::MAX)
     }
 
     pub fn set_expired(&mut self) {
         self.expired = true;
     }
 
-    pub(crate) fn rehydrate(
+    fn check_signer(&self, signer: Address) -> std::result::Result<(), PatError> {
+        if signer != self.owner {
+            return Err(PatError::OwnerMismatch(self.owner, signer));
+        }
+
+        Ok(())
+    }
+
+    /// Take the batch parameters from the batch as it is in `store`, eg. once it has been
+    /// diluted, and stamp with `signer` from now on.
+    pub fn rehydrate(
         &mut self,
-        store: &Store,
-        signer: LocalWallet,
+        store: &dyn BatchStore,
+        signer: PrivateKeySigner,
     ) -> std::result::Result<(), PatError> {
         let batch = store
             .get(self.batch_id)
             .ok_or(PatError::BatchNotFound(self.batch_id))?;
+        self.apply_batch(&batch);
+        self.signer = Some(signer);
+        Ok(())
+    }
+
+    pub(crate) fn batch_id(&self) -> BatchId {
+        self.batch_id
+    }
+
+    // take the batch parameters, which aren't serialised, from the batch being stamped with
+    pub(crate) fn apply_batch(&mut self, batch: &Batch) {
         self.batch_depth = batch.depth;
         self.batch_bucket_depth = batch.bucket_depth;
         self.block_created = batch.block_created;
+        self.owner = batch.owner;
         self.immutable = batch.immutable;
-        Ok(())
+        // pats saved before wraparounds were tracked have none recorded
+        self.wraps.resize(self.buckets.len(), 0);
     }
 }
 
+/// The index of a stamp, its bucket `x` in the upper 32 bits and the position `y` within the
+/// bucket in the lower 32 bits
+fn stamp_index(x: u32, y: u32) -> u64 {
+    ((x as u64) << 32) | y as u64
+}
+
 pub(crate) trait BucketSeeker {
     fn get_x(&self, bucket_depth: u32) -> u32;
 }
@@ -166,11 +374,12 @@ impl BucketSeeker for Chunk {
 
 #[cfg(test)]
 mod tests {
-    use ethers_core::types::Address;
     use hex::ToHex;
 
     use super::*;
-    use bmt::chunk::Options;
+    use crate::batch::{parse_batch_id, InMemoryBatchStore};
+    use crate::clock::MockClock;
+    use file::chunk::Options;
 
     static BATCH_ID: &str = "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369";
     static STAMP_MARSHALLED: &str = "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d367613690000cbe5000000000000018921ff0dbb29169df9e6364e26c6ca6b17745c10b9d6a36ea38e204f2e3cc64a8373c0661f5bb0a347c61d8d1689b0dcf8354117686a6a18d08cff927f526de5fc61b2b7491b";
@@ -180,20 +389,15 @@ mod tests {
 
     #[tokio::test]
     async fn valid_stamp() {
-        let chunks = bmt::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
+        let chunks = file::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
         let chunk = chunks.leaf_chunks()[0].clone();
 
-        // convert BATCH_ID to String with type annotations on into()
-        let batch_id = hex::decode::<String>(BATCH_ID.to_owned().into()).unwrap();
-
-        // convert batch_id to [u8; 32]
-        let mut batch_id_arr = [0u8; 32];
-        batch_id_arr.copy_from_slice(&batch_id);
+        let batch_id = parse_batch_id(BATCH_ID).unwrap();
 
-        let wallet = PRIVATE_KEY.parse::<LocalWallet>().unwrap();
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
 
         // create a batch
-        let batch = Batch::new(batch_id_arr, 0, None, Address::zero(), 18, 16, false);
+        let batch = Batch::new(batch_id, 0, None, wallet.address(), 18, 16, false).unwrap();
         let mut pat = Pat::new(&batch, 0, false, wallet);
 
         let chunk = pat.stamp(chunk, Some(TIMESTAMP)).await.unwrap();
@@ -203,4 +407,388 @@ mod tests {
             STAMP_MARSHALLED.to_owned()
         );
     }
+
+    #[test]
+    fn valid_stamp_sync() {
+        let chunks = file::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
+        let chunk = chunks.leaf_chunks()[0].clone();
+
+        let batch_id = parse_batch_id(BATCH_ID).unwrap();
+
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+
+        let batch = Batch::new(batch_id, 0, None, wallet.address(), 18, 16, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+
+        let chunk = pat.stamp_sync(chunk, Some(TIMESTAMP), &wallet).unwrap();
+
+        assert_eq!(
+            chunk.stamp().unwrap().encode_hex::<String>(),
+            STAMP_MARSHALLED.to_owned()
+        );
+    }
+
+    #[test]
+    fn occupancy_bounded_by_bucket_count() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+
+        // a deep batch is tracked with one counter per bucket, not one entry per stamp
+        for depth in [17, 24, 40] {
+            let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, depth, 16, true).unwrap();
+            let pat = Pat::new(&batch, 0, false, wallet.clone());
+
+            assert_eq!(pat.buckets.len(), 1 << 16);
+            assert_eq!(pat.wraps.len(), 1 << 16);
+            assert_eq!(pat.remaining_capacity(), 1u64 << depth);
+        }
+    }
+
+    #[test]
+    fn rehydrate_from_store() {
+        let store = InMemoryBatchStore::new();
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+
+        let mut batch = Batch::new([1u8; 32], 1_400, None, Address::ZERO, 18, 16, false).unwrap();
+        store.put(batch.clone());
+
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+        assert_eq!(pat.bucket_upper_bound(), 4);
+
+        // the batch is diluted on chain, the pat picks up the new depth
+        batch.dilute(20, 1_000).unwrap();
+        store.put(batch);
+        pat.rehydrate(&store, wallet.clone()).unwrap();
+        assert_eq!(pat.bucket_upper_bound(), 16);
+
+        // once the batch has expired and been removed, there is nothing to rehydrate from
+        assert!(store.expire([1u8; 32]).is_some());
+        assert!(matches!(
+            pat.rehydrate(&store, wallet),
+            Err(PatError::BatchNotFound(id)) if id == [1u8; 32]
+        ));
+    }
+
+    #[tokio::test]
+    async fn restored_pat_stamps_once_rehydrated() {
+        let chunks = file::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
+        let chunk = chunks.leaf_chunks()[0].clone();
+
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let batch = Batch::new([1u8; 32], 0, None, wallet.address(), 18, 16, false).unwrap();
+        let store = InMemoryBatchStore::new();
+        store.put(batch.clone());
+
+        let mut saved = Vec::new();
+        let pat = Pat::new(&batch, 0, false, wallet.clone());
+        store.save(&[pat], &mut saved).unwrap();
+
+        // signers aren't saved, so the restored pat has none to stamp with
+        let (store, mut pats) = InMemoryBatchStore::restore(saved.as_slice()).unwrap();
+        let pat = &mut pats[0];
+        assert!(matches!(
+            pat.stamp(chunk.clone(), Some(TIMESTAMP)).await,
+            Err(PatError::NoSigner)
+        ));
+        assert_eq!(pat.utilization(), 0);
+
+        pat.rehydrate(&store, wallet).unwrap();
+        assert!(pat.stamp(chunk, Some(TIMESTAMP)).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn expired_batch_refuses_stamping() {
+        let chunks = file::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
+        let chunk = chunks.leaf_chunks()[0].clone();
+
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+
+        let batch = Batch::new([1u8; 32], 0, None, wallet.address(), 18, 16, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+        pat.set_expired();
+
+        assert!(matches!(
+            pat.stamp(chunk, Some(TIMESTAMP)).await,
+            Err(PatError::BatchExpired(id)) if id == [1u8; 32]
+        ));
+        assert_eq!(pat.utilization(), 0);
+    }
+
+    /// Chunks with distinct payloads, all falling in the same collision bucket
+    fn chunks_in_bucket(bucket_depth: u32, n: usize) -> (u32, Vec<Chunk>) {
+        let chunks = (0u32..).map(|i| {
+            let file = file::file::ChunkedFile::new(i.to_be_bytes().to_vec(), Options::default());
+            file.leaf_chunks()[0].clone()
+        });
+
+        let mut buckets = vec![Vec::new(); 1 << bucket_depth];
+        for chunk in chunks {
+            let x = chunk.get_x(bucket_depth);
+            buckets[x as usize].push(chunk);
+            if buckets[x as usize].len() == n {
+                return (x, buckets.swap_remove(x as usize));
+            }
+        }
+
+        unreachable!()
+    }
+
+    #[test]
+    fn inc_assigns_each_index_once() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (bucket, chunks) = chunks_in_bucket(2, 5);
+
+        // immutable: capacity 2^(4-2) = 4 per bucket, the fifth stamp is refused
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, true).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+        assert_eq!(pat.bucket_upper_bound(), 4);
+
+        let indices: Vec<u32> = chunks[..4]
+            .iter()
+            .map(|chunk| {
+                let (x, y) = pat.inc(chunk).unwrap();
+                assert_eq!(x, bucket);
+                y
+            })
+            .collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        assert_eq!(pat.utilization(), 4);
+        assert!(matches!(pat.inc(&chunks[4]), Err(PatError::BucketFull())));
+
+        // mutable: the bucket wraps around to the first index
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+
+        let indices: Vec<u32> = chunks.iter().map(|c| pat.inc(c).unwrap().1).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3, 0]);
+        assert_eq!(pat.utilization(), 4);
+    }
+
+    #[test]
+    fn wrapped_buckets() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (bucket, chunks) = chunks_in_bucket(2, 5);
+
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+
+        // filling the bucket is not a wrap
+        for chunk in &chunks[..4] {
+            pat.inc(chunk).unwrap();
+        }
+        assert!(pat.wrapped_buckets().is_empty());
+
+        pat.inc(&chunks[4]).unwrap();
+        assert_eq!(pat.wrapped_buckets(), vec![(bucket, 1)]);
+    }
+
+    #[test]
+    fn remaining_capacity() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (_, chunks) = chunks_in_bucket(2, 5);
+
+        // 4 buckets of 4 slots each
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, true).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+        assert_eq!(pat.remaining_capacity(), 16);
+        assert!(!pat.is_full());
+
+        pat.inc(&chunks[0]).unwrap();
+        pat.inc(&chunks[1]).unwrap();
+        assert_eq!(pat.remaining_capacity(), 14);
+
+        // a full bucket refuses further stamps, but the batch is not full
+        pat.inc(&chunks[2]).unwrap();
+        pat.inc(&chunks[3]).unwrap();
+        assert!(matches!(pat.inc(&chunks[4]), Err(PatError::BucketFull())));
+        assert_eq!(pat.remaining_capacity(), 12);
+        assert!(!pat.is_full());
+
+        pat.buckets.iter_mut().for_each(|count| *count = 4);
+        assert_eq!(pat.remaining_capacity(), 0);
+        assert!(pat.is_full());
+    }
+
+    #[test]
+    fn bucket_stats() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (bucket, chunks) = chunks_in_bucket(2, 4);
+
+        // 4 buckets of 4 slots each
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, true).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+        let stats = pat.bucket_stats();
+        assert_eq!(
+            (stats.min, stats.max, stats.mean, stats.full),
+            (0, 0, 0.0, 0)
+        );
+
+        for chunk in &chunks {
+            pat.inc(chunk).unwrap();
+        }
+        pat.buckets[(bucket as usize + 1) % 4] = 2;
+
+        // fills are 4, 2, 0 and 0 in some order
+        let stats = pat.bucket_stats();
+        assert_eq!(stats.min, 0);
+        assert_eq!(stats.max, 4);
+        assert_eq!(stats.mean, 1.5);
+        assert_eq!(stats.full, 1);
+    }
+
+    #[test]
+    fn can_stamp_checks_bucket() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (bucket, chunks) = chunks_in_bucket(2, 5);
+        let other = (0u32..)
+            .map(|i| {
+                let file =
+                    file::file::ChunkedFile::new(i.to_le_bytes().to_vec(), Options::default());
+                file.leaf_chunks()[0].clone()
+            })
+            .find(|chunk| chunk.get_x(2) != bucket)
+            .unwrap();
+
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, true).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+        for chunk in &chunks[..4] {
+            assert!(pat.can_stamp(chunk));
+            pat.inc(chunk).unwrap();
+        }
+
+        // the batch has capacity left, but not in the bucket of the chunk
+        assert!(!pat.is_full());
+        assert!(!pat.can_stamp(&chunks[4]));
+        assert!(pat.can_stamp(&other));
+
+        // a mutable batch wraps the bucket around instead
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+        for chunk in &chunks[..4] {
+            pat.inc(chunk).unwrap();
+        }
+        assert!(pat.can_stamp(&chunks[4]));
+
+        pat.set_expired();
+        assert!(!pat.can_stamp(&other));
+    }
+
+    #[test]
+    fn can_stamp_near_full_immutable_bucket() {
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (bucket, chunks) = chunks_in_bucket(2, 5);
+
+        let batch = Batch::new([1u8; 32], 0, None, Address::ZERO, 4, 2, true).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+        for chunk in &chunks[..3] {
+            pat.inc(chunk).unwrap();
+        }
+
+        // the last slot of the bucket is still free, and checking doesn't take it
+        assert!(pat.can_stamp(&chunks[3]));
+        assert!(pat.can_stamp(&chunks[3]));
+        assert_eq!(pat.buckets[bucket as usize], 3);
+
+        pat.inc(&chunks[3]).unwrap();
+        assert!(!pat.can_stamp(&chunks[4]));
+        assert!(matches!(pat.inc(&chunks[4]), Err(PatError::BucketFull())));
+
+        // every other bucket is empty and still accepts chunks
+        for x in (0..4).filter(|x| *x != bucket as usize) {
+            assert_eq!(pat.buckets[x], 0);
+        }
+        assert_eq!(pat.remaining_capacity(), 12);
+    }
+
+    #[tokio::test]
+    async fn stamp_with_shared_owner_key() {
+        use crate::stamp::{MarshalledStamp, StampValidator};
+
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let (alice, bob) = (wallet.clone(), wallet.clone());
+        let (_, chunks) = chunks_in_bucket(2, 2);
+
+        let batch = Batch::new([1u8; 32], 0, None, wallet.address(), 4, 2, true).unwrap();
+        let store = InMemoryBatchStore::new();
+        store.put(batch.clone());
+
+        let mut pat = Pat::new(&batch, 0, false, wallet);
+        let stamped = [
+            pat.stamp_with(chunks[0].clone(), Some(TIMESTAMP), &alice)
+                .await
+                .unwrap(),
+            pat.stamp_with(chunks[1].clone(), Some(TIMESTAMP), &bob)
+                .await
+                .unwrap(),
+        ];
+
+        // both signers draw from the same bucket
+        assert_eq!(pat.utilization(), 2);
+        for (chunk, stamped) in chunks.iter().zip(stamped) {
+            let stamp: MarshalledStamp = stamped.stamp().unwrap().try_into().unwrap();
+            (store.validate_stamp())(&mut chunk.clone(), stamp).unwrap();
+        }
+
+        // a signer for another address is refused without using up a position
+        let mallory = "0000000000000000000000000000000000000000000000000000000000000001"
+            .parse::<PrivateKeySigner>()
+            .unwrap();
+        assert!(matches!(
+            pat.stamp_with(chunks[0].clone(), Some(TIMESTAMP), &mallory).await,
+            Err(PatError::OwnerMismatch(owner, signer))
+                if owner == alice.address() && signer == mallory.address()
+        ));
+        assert!(matches!(
+            pat.stamp_sync(chunks[0].clone(), Some(TIMESTAMP), &mallory),
+            Err(PatError::OwnerMismatch(..))
+        ));
+        assert_eq!(pat.utilization(), 2);
+    }
+
+    #[tokio::test]
+    async fn stamp_with_mock_clock() {
+        let chunks = file::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
+        let chunk = chunks.leaf_chunks()[0].clone();
+
+        let batch_id = parse_batch_id(BATCH_ID).unwrap();
+
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+        let clock = MockClock::new(TIMESTAMP);
+
+        let batch = Batch::new(batch_id, 0, None, wallet.address(), 18, 16, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet).with_clock(clock.clone());
+
+        // without a timestamp, the stamp is timestamped by the clock
+        let stamped = pat.stamp(chunk.clone(), None).await.unwrap();
+        assert_eq!(
+            stamped.stamp().unwrap().encode_hex::<String>(),
+            STAMP_MARSHALLED.to_owned()
+        );
+
+        clock.advance(1);
+        let request = pat.prepare(&chunk, None).unwrap();
+        assert_eq!(request.timestamp, TIMESTAMP + 1);
+    }
+
+    #[tokio::test]
+    async fn prepare_and_finalize_matches_stamp() {
+        let chunks = file::file::ChunkedFile::new(PAYLOAD.to_owned().into(), Options::default());
+        let chunk = chunks.leaf_chunks()[0].clone();
+
+        let batch_id = parse_batch_id(BATCH_ID).unwrap();
+
+        let wallet = PRIVATE_KEY.parse::<PrivateKeySigner>().unwrap();
+
+        let batch = Batch::new(batch_id, 0, None, Address::ZERO, 18, 16, false).unwrap();
+        let mut pat = Pat::new(&batch, 0, false, wallet.clone());
+
+        // sign the digest out of band, as a remote signer would
+        let request = pat.prepare(&chunk, Some(TIMESTAMP)).unwrap();
+        let signature = wallet.sign_message(&request.digest).await.unwrap();
+        let stamp = pat.finalize(request, signature);
+
+        assert_eq!(
+            stamp.to_bytes().encode_hex::<String>(),
+            STAMP_MARSHALLED.to_owned()
+        );
+    }
 }
@@ -1,6 +1,6 @@
 use std::{io, ops::Deref, sync::OnceLock};
 
-use crate::{ChunkAddress, BRANCHES, SEGMENT_SIZE};
+use crate::{ChunkAddress, Span, BRANCHES, SEGMENT_SIZE};
 use alloy::{
     primitives::{Address, PrimitiveSignature, SignatureError},
     signers::Error as SignerError,
@@ -91,9 +91,7 @@ impl<T: Chunk> ChunkData for CachedChunk<T> {
 
 impl<T: Chunk> Chunk for CachedChunk<T> {
     fn address(&self) -> ChunkAddress {
-        self.cached_address
-            .get_or_init(|| self.inner.address())
-            .clone()
+        *self.cached_address.get_or_init(|| self.inner.address())
     }
 }
 
@@ -114,6 +112,9 @@ pub enum ChunkError {
         limit: usize,
     },
 
+    #[error("Span mismatch (span: {span}, data length: {data_len})")]
+    SpanMismatch { span: Span, data_len: usize },
+
     #[error("Invalid chunk format: {0}")]
     Format(&'static str),
 
@@ -150,6 +151,10 @@ impl ChunkError {
         }
     }
 
+    pub fn span_mismatch(span: Span, data_len: usize) -> Self {
+        Self::SpanMismatch { span, data_len }
+    }
+
     pub fn format(msg: &'static str) -> Self {
         Self::Format(msg)
     }
@@ -0,0 +1,216 @@
+use crate::SwarmAddress;
+use thiserror::Error;
+
+/// RFC 4648 base32 alphabet, lowercase
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+/// Bitcoin base58 alphabet
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EncodingError {
+    #[error("Invalid {encoding} character: {character:?}")]
+    InvalidCharacter {
+        encoding: &'static str,
+        character: char,
+    },
+
+    #[error("Invalid {encoding} address length: {len}")]
+    InvalidLength { encoding: &'static str, len: usize },
+}
+
+/// Alternative text encodings of an address, for clients that don't use hex.
+pub trait AddressEncoding: Sized {
+    /// Encode as unpadded lowercase RFC 4648 base32
+    fn to_base32(&self) -> String;
+
+    /// Decode from unpadded RFC 4648 base32, in either case
+    fn from_base32(s: &str) -> Result<Self, EncodingError>;
+
+    /// Encode as base58 using the bitcoin alphabet
+    fn to_base58(&self) -> String;
+
+    /// Decode from base58 using the bitcoin alphabet
+    fn from_base58(s: &str) -> Result<Self, EncodingError>;
+}
+
+impl AddressEncoding for SwarmAddress {
+    fn to_base32(&self) -> String {
+        let mut out = String::with_capacity((self.len() * 8).div_ceil(5));
+        let mut buffer = 0u32;
+        let mut bits = 0;
+
+        for byte in self.iter() {
+            buffer = (buffer << 8) | *byte as u32;
+            bits += 8;
+
+            while bits >= 5 {
+                bits -= 5;
+                out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+            }
+            buffer &= (1 << bits) - 1;
+        }
+
+        if bits > 0 {
+            out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+        }
+
+        out
+    }
+
+    fn from_base32(s: &str) -> Result<Self, EncodingError> {
+        let mut bytes = Vec::with_capacity(Self::len_bytes());
+        let mut buffer = 0u32;
+        let mut bits = 0;
+
+        for c in s.chars() {
+            let value = BASE32_ALPHABET
+                .iter()
+                .position(|a| *a as char == c.to_ascii_lowercase())
+                .ok_or(EncodingError::InvalidCharacter {
+                    encoding: "base32",
+                    character: c,
+                })?;
+
+            buffer = (buffer << 5) | value as u32;
+            bits += 5;
+
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((buffer >> bits) as u8);
+            }
+            buffer &= (1 << bits) - 1;
+        }
+
+        Self::try_from(bytes.as_slice()).map_err(|_| EncodingError::InvalidLength {
+            encoding: "base32",
+            len: bytes.len(),
+        })
+    }
+
+    fn to_base58(&self) -> String {
+        let zeros = self.iter().take_while(|b| **b == 0).count();
+
+        // base58 digits, least significant first
+        let mut digits = Vec::<u8>::with_capacity(self.len() * 138 / 100 + 1);
+        for byte in &self[zeros..] {
+            let mut carry = *byte as u32;
+            for digit in digits.iter_mut() {
+                carry += (*digit as u32) << 8;
+                *digit = (carry % 58) as u8;
+                carry /= 58;
+            }
+
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+
+        std::iter::repeat_n('1', zeros)
+            .chain(
+                digits
+                    .iter()
+                    .rev()
+                    .map(|d| BASE58_ALPHABET[*d as usize] as char),
+            )
+            .collect()
+    }
+
+    fn from_base58(s: &str) -> Result<Self, EncodingError> {
+        let zeros = s.chars().take_while(|c| *c == '1').count();
+
+        // decoded bytes, least significant first
+        let mut bytes = Vec::<u8>::with_capacity(Self::len_bytes());
+        for c in s.chars().skip(zeros) {
+            let mut carry = BASE58_ALPHABET.iter().position(|a| *a as char == c).ok_or(
+                EncodingError::InvalidCharacter {
+                    encoding: "base58",
+                    character: c,
+                },
+            )? as u32;
+
+            for byte in bytes.iter_mut() {
+                carry += (*byte as u32) * 58;
+                *byte = (carry & 0xff) as u8;
+                carry >>= 8;
+            }
+
+            while carry > 0 {
+                bytes.push((carry & 0xff) as u8);
+                carry >>= 8;
+            }
+        }
+
+        bytes.extend(std::iter::repeat_n(0, zeros));
+        bytes.reverse();
+
+        Self::try_from(bytes.as_slice()).map_err(|_| EncodingError::InvalidLength {
+            encoding: "base58",
+            len: bytes.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+
+    const ADDRESS: SwarmAddress =
+        b256!("cbe563e4865fd01948a1180081bbb7e144204344012dea8ce6e86d36dbc63495");
+
+    #[test]
+    fn base32_round_trip() {
+        let encoded = ADDRESS.to_base32();
+        assert_eq!(
+            encoded,
+            "zpswhzegl7ibssfbdaaido5x4fccaq2eaew6vdhg5bwtnw6ggskq"
+        );
+        assert_eq!(SwarmAddress::from_base32(&encoded).unwrap(), ADDRESS);
+        assert_eq!(
+            SwarmAddress::from_base32(&encoded.to_uppercase()).unwrap(),
+            ADDRESS
+        );
+
+        let zero = SwarmAddress::ZERO;
+        assert_eq!(SwarmAddress::from_base32(&zero.to_base32()).unwrap(), zero);
+    }
+
+    #[test]
+    fn base58_round_trip() {
+        let encoded = ADDRESS.to_base58();
+        assert_eq!(encoded, "EivdvD2s5vnX4ghcgjkvMJzZ5QWS43sumVZqXdFbh2nY");
+        assert_eq!(SwarmAddress::from_base58(&encoded).unwrap(), ADDRESS);
+
+        // leading zero bytes are encoded as leading ones
+        let zero = SwarmAddress::ZERO;
+        assert_eq!(zero.to_base58(), "1".repeat(32));
+        assert_eq!(SwarmAddress::from_base58(&zero.to_base58()).unwrap(), zero);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(
+            SwarmAddress::from_base32("zpsw1"),
+            Err(EncodingError::InvalidCharacter {
+                encoding: "base32",
+                character: '1'
+            })
+        );
+        assert_eq!(
+            SwarmAddress::from_base58("0Eivd"),
+            Err(EncodingError::InvalidCharacter {
+                encoding: "base58",
+                character: '0'
+            })
+        );
+        assert!(matches!(
+            SwarmAddress::from_base58("Eivd"),
+            Err(EncodingError::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            SwarmAddress::from_base32("zpsw"),
+            Err(EncodingError::InvalidLength { .. })
+        ));
+    }
+}
@@ -1,10 +1,18 @@
 use alloy::primitives::FixedBytes;
 
 pub mod chunk;
+pub mod encoding;
+pub mod reference;
 pub mod stamp;
+pub mod store;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
 pub use chunk::*;
+pub use encoding::{AddressEncoding, EncodingError};
+pub use reference::BzzReference;
 pub use stamp::Stamp;
+pub use store::ChunkStore;
 
 const HASH_SIZE: usize = 32;
 pub const SEGMENT_SIZE: usize = HASH_SIZE;
@@ -19,3 +27,6 @@ pub type ChunkAddress = SwarmAddress;
 pub type Segment = [u8; SEGMENT_SIZE];
 pub type Span = u64;
 pub const SPAN_SIZE: usize = std::mem::size_of::<Span>();
+
+// Postage
+pub type BatchId = FixedBytes<32>;
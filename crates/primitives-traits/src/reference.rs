@@ -0,0 +1,161 @@
+use std::{fmt, str::FromStr};
+
+use alloy::hex::{self, FromHexError};
+
+use crate::{ChunkAddress, EncodingError, SEGMENT_SIZE};
+
+/// Length of the key following the address in an encrypted reference
+pub const REFERENCE_KEY_SIZE: usize = 32;
+
+/// A reference to content in Swarm, either the bare address of its root chunk or, for encrypted
+/// content, the address followed by the key decrypting the root chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BzzReference {
+    Plain(ChunkAddress),
+    Encrypted {
+        address: ChunkAddress,
+        key: [u8; REFERENCE_KEY_SIZE],
+    },
+}
+
+impl BzzReference {
+    /// The address of the root chunk
+    pub fn address(&self) -> ChunkAddress {
+        match self {
+            Self::Plain(address) | Self::Encrypted { address, .. } => *address,
+        }
+    }
+
+    /// The key decrypting the root chunk, if the reference is encrypted
+    pub fn key(&self) -> Option<&[u8; REFERENCE_KEY_SIZE]> {
+        match self {
+            Self::Plain(_) => None,
+            Self::Encrypted { key, .. } => Some(key),
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        matches!(self, Self::Encrypted { .. })
+    }
+
+    /// The serialised reference, 32 bytes if plain and 64 bytes if encrypted
+    pub fn to_vec(&self) -> Vec<u8> {
+        match self {
+            Self::Plain(address) => address.to_vec(),
+            Self::Encrypted { address, key } => [address.as_slice(), key].concat(),
+        }
+    }
+}
+
+impl From<ChunkAddress> for BzzReference {
+    fn from(address: ChunkAddress) -> Self {
+        Self::Plain(address)
+    }
+}
+
+impl TryFrom<&[u8]> for BzzReference {
+    type Error = EncodingError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        match bytes.len() {
+            SEGMENT_SIZE => Ok(Self::Plain(ChunkAddress::from_slice(bytes))),
+            len if len == SEGMENT_SIZE + REFERENCE_KEY_SIZE => {
+                let (address, key) = bytes.split_at(SEGMENT_SIZE);
+                Ok(Self::Encrypted {
+                    address: ChunkAddress::from_slice(address),
+                    key: key.try_into().unwrap(),
+                })
+            }
+            len => Err(EncodingError::InvalidLength {
+                encoding: "hex",
+                len,
+            }),
+        }
+    }
+}
+
+impl fmt::Display for BzzReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.to_vec()))
+    }
+}
+
+impl FromStr for BzzReference {
+    type Err = EncodingError;
+
+    /// Parse a hex encoded reference, with or without a `0x` prefix, picking the variant by its
+    /// length: 64 characters for a plain reference and 128 for an encrypted one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s).map_err(|e| match e {
+            FromHexError::InvalidHexCharacter { c, .. } => EncodingError::InvalidCharacter {
+                encoding: "hex",
+                character: c,
+            },
+            _ => EncodingError::InvalidLength {
+                encoding: "hex",
+                len: s.len() / 2,
+            },
+        })?;
+
+        Self::try_from(bytes.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+
+    const ADDRESS: ChunkAddress =
+        b256!("cbe563e4865fd01948a1180081bbb7e144204344012dea8ce6e86d36dbc63495");
+
+    #[test]
+    fn plain_round_trip() {
+        let s = "cbe563e4865fd01948a1180081bbb7e144204344012dea8ce6e86d36dbc63495";
+
+        let reference: BzzReference = s.parse().unwrap();
+        assert_eq!(reference, BzzReference::Plain(ADDRESS));
+        assert_eq!(reference.to_string(), s);
+        assert_eq!(reference.key(), None);
+
+        // a 0x prefix is accepted
+        assert_eq!(format!("0x{}", s).parse::<BzzReference>(), Ok(reference));
+    }
+
+    #[test]
+    fn encrypted_round_trip() {
+        let s = "cbe563e4865fd01948a1180081bbb7e144204344012dea8ce6e86d36dbc63495\
+                 0707070707070707070707070707070707070707070707070707070707070707";
+
+        let reference: BzzReference = s.parse().unwrap();
+        assert_eq!(
+            reference,
+            BzzReference::Encrypted {
+                address: ADDRESS,
+                key: [7; 32]
+            }
+        );
+        assert_eq!(reference.address(), ADDRESS);
+        assert_eq!(reference.to_string(), s);
+        assert_eq!(reference.to_vec().len(), 64);
+        assert_eq!(
+            BzzReference::try_from(reference.to_vec().as_slice()),
+            Ok(reference)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_reference() {
+        assert_eq!(
+            "cbe5".parse::<BzzReference>(),
+            Err(EncodingError::InvalidLength {
+                encoding: "hex",
+                len: 2
+            })
+        );
+        assert!(matches!(
+            "zz".repeat(32).parse::<BzzReference>(),
+            Err(EncodingError::InvalidCharacter { character: 'z', .. })
+        ));
+    }
+}
@@ -1,12 +1,17 @@
 use alloy::{primitives::B256, signers::Signature};
+use bytes::Bytes;
 
-const STAMP_INDEX_SIZE: usize = std::mem::size_of::<u64>();
-const STAMP_TIMESTAMP_SIZE: usize = std::mem::size_of::<u64>();
+use crate::BatchId;
+
+pub const STAMP_INDEX_SIZE: usize = std::mem::size_of::<u64>();
+pub const STAMP_TIMESTAMP_SIZE: usize = std::mem::size_of::<u64>();
 
 pub trait Stamp {
-    fn batch_id(&self) -> B256;
+    fn batch_id(&self) -> BatchId;
     fn index(&self) -> u64;
     fn sig(&self) -> Signature;
     fn timestamp(&self) -> u64;
     fn hash(&self) -> B256;
+    /// The stamp serialised in Bee's wire format
+    fn to_bytes(&self) -> Bytes;
 }
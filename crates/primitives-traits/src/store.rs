@@ -0,0 +1,19 @@
+use std::future::Future;
+
+use crate::{chunk::Result, Chunk, ChunkAddress};
+
+/// Asynchronous storage of chunks by address, for anything that needs to fetch chunks without
+/// caring where they are kept.
+pub trait ChunkStore: Send + Sync {
+    /// The type of chunk held by the store
+    type Chunk: Chunk;
+
+    /// Get the chunk at `address`, returning `None` if it isn't stored
+    fn get(
+        &self,
+        address: ChunkAddress,
+    ) -> impl Future<Output = Result<Option<Self::Chunk>>> + Send;
+
+    /// Store a chunk, returning its address
+    fn put(&self, chunk: Self::Chunk) -> impl Future<Output = Result<ChunkAddress>> + Send;
+}
@@ -0,0 +1,65 @@
+use crate::SwarmAddress;
+use rand::RngCore;
+
+/// Random addresses for tests, eg. to fill a particular postage bucket or neighbourhood.
+pub trait RandomAddress: Sized {
+    /// A uniformly random address
+    fn random() -> Self;
+
+    /// A random address sharing its first `po` bits with `prefix`, so that its proximity order
+    /// to `prefix` is at least `po`
+    fn random_with_prefix(prefix: &Self, po: u8) -> Self;
+}
+
+impl RandomAddress for SwarmAddress {
+    fn random() -> Self {
+        let mut address = SwarmAddress::ZERO;
+        rand::thread_rng().fill_bytes(address.as_mut_slice());
+        address
+    }
+
+    fn random_with_prefix(prefix: &Self, po: u8) -> Self {
+        let mut address = <Self as RandomAddress>::random();
+        let bytes = po as usize / 8;
+        let bits = po % 8;
+
+        address[..bytes].copy_from_slice(&prefix[..bytes]);
+        if bits > 0 {
+            let mask = 0xffu8 << (8 - bits);
+            address[bytes] = (prefix[bytes] & mask) | (address[bytes] & !mask);
+        }
+
+        address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn common_prefix_bits(x: &SwarmAddress, y: &SwarmAddress) -> u32 {
+        (*x ^ *y)
+            .iter()
+            .position(|b| *b != 0)
+            .map(|i| i as u32 * 8 + (x[i] ^ y[i]).leading_zeros())
+            .unwrap_or(256)
+    }
+
+    #[test]
+    fn random_with_prefix_keeps_leading_bits() {
+        let prefix = <SwarmAddress as RandomAddress>::random();
+
+        for po in [0u8, 1, 7, 8, 13, 16, 31, 255] {
+            for _ in 0..16 {
+                let address = SwarmAddress::random_with_prefix(&prefix, po);
+                assert!(common_prefix_bits(&prefix, &address) >= po as u32);
+            }
+        }
+
+        // the bits past the prefix are random
+        let addresses: Vec<_> = (0..16)
+            .map(|_| SwarmAddress::random_with_prefix(&prefix, 8))
+            .collect();
+        assert!(addresses.iter().any(|a| a != &addresses[0]));
+    }
+}
@@ -0,0 +1,153 @@
+use alloy::{
+    primitives::Keccak256,
+    signers::k256::{
+        ecdsa::VerifyingKey, elliptic_curve::sec1::ToEncodedPoint, ProjectivePoint, SecretKey,
+    },
+};
+use thiserror::Error;
+
+/// Length of the access keys and of the keys they wrap
+pub const ACCESS_KEY_SIZE: usize = 32;
+
+pub type AccessKey = [u8; ACCESS_KEY_SIZE];
+
+/// Nonce of the session key under which the wrapped access key is looked up
+const LOOKUP_KEY_NONCE: u8 = 0x00;
+
+/// Nonce of the session key wrapping the access key
+const ACCESS_KEY_NONCE: u8 = 0x01;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum AccessError {
+    #[error("Invalid root key")]
+    InvalidRootKey,
+}
+
+/// Derive the key wrapping the access key shared between the holder of the private key `root`
+/// and the holder of `publisher`, ie. `keccak256(x || 0x01)` where `x` is the x coordinate of
+/// the ECDH shared secret.
+///
+/// As in Bee, a publisher and a grantee derive the same key from their own private key and the
+/// other's public key, so that a key wrapped by the publisher can only be unwrapped by grantees.
+pub fn derive_access_key(
+    root: AccessKey,
+    publisher: &VerifyingKey,
+) -> Result<AccessKey, AccessError> {
+    session_key(root, publisher, ACCESS_KEY_NONCE)
+}
+
+/// Derive the key under which the wrapped access key is looked up, ie. `keccak256(x || 0x00)`
+/// where `x` is the x coordinate of the ECDH shared secret.
+pub fn derive_lookup_key(
+    root: AccessKey,
+    publisher: &VerifyingKey,
+) -> Result<AccessKey, AccessError> {
+    session_key(root, publisher, LOOKUP_KEY_NONCE)
+}
+
+fn session_key(
+    root: AccessKey,
+    publisher: &VerifyingKey,
+    nonce: u8,
+) -> Result<AccessKey, AccessError> {
+    let secret = SecretKey::from_slice(&root).map_err(|_| AccessError::InvalidRootKey)?;
+    let shared = (ProjectivePoint::from(*publisher.as_affine()) * *secret.to_nonzero_scalar())
+        .to_affine()
+        .to_encoded_point(false);
+    let x = shared.x().expect("shared secret is not the identity");
+
+    // the x coordinate is hashed as a big-endian integer, without leading zero bytes
+    let first = x.iter().position(|b| *b != 0).unwrap_or(x.len());
+
+    let mut hasher = Keccak256::new();
+    hasher.update(&x[first..]);
+    hasher.update([nonce]);
+    Ok(hasher.finalize().0)
+}
+
+/// Wrap `key`, eg. a file's root key or reference, with the access key. Wrapping is the counter
+/// mode keccak encryption of a single segment used for chunks, so it is its own inverse.
+pub fn wrap_key(access_key: &AccessKey, key: &[u8; ACCESS_KEY_SIZE]) -> [u8; ACCESS_KEY_SIZE] {
+    let mut hasher = Keccak256::new();
+    hasher.update(access_key);
+    hasher.update(0u32.to_le_bytes());
+    let ctr_hash = hasher.finalize();
+
+    let mut hasher = Keccak256::new();
+    hasher.update(ctr_hash);
+    let segment_key = hasher.finalize();
+
+    std::array::from_fn(|i| key[i] ^ segment_key[i])
+}
+
+/// Unwrap a key wrapped with [`wrap_key`]
+pub fn unwrap_key(
+    access_key: &AccessKey,
+    wrapped: &[u8; ACCESS_KEY_SIZE],
+) -> [u8; ACCESS_KEY_SIZE] {
+    wrap_key(access_key, wrapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{primitives::B256, signers::local::PrivateKeySigner};
+
+    #[test]
+    fn publisher_and_grantee_derive_the_same_keys() {
+        let publisher = PrivateKeySigner::random();
+        let grantee = PrivateKeySigner::random();
+
+        let publisher_root = publisher.to_bytes().0;
+        let grantee_root = grantee.to_bytes().0;
+
+        let access_key =
+            derive_access_key(publisher_root, grantee.credential().verifying_key()).unwrap();
+        assert_eq!(
+            derive_access_key(grantee_root, publisher.credential().verifying_key()).unwrap(),
+            access_key
+        );
+
+        let lookup_key =
+            derive_lookup_key(publisher_root, grantee.credential().verifying_key()).unwrap();
+        assert_eq!(
+            derive_lookup_key(grantee_root, publisher.credential().verifying_key()).unwrap(),
+            lookup_key
+        );
+        assert_ne!(lookup_key, access_key);
+
+        // another grantee derives another key
+        let other = PrivateKeySigner::random();
+        assert_ne!(
+            derive_access_key(other.to_bytes().0, publisher.credential().verifying_key()).unwrap(),
+            access_key
+        );
+    }
+
+    #[test]
+    fn wrap_unwrap_round_trip() {
+        let publisher = PrivateKeySigner::random();
+        let grantee = PrivateKeySigner::random();
+        let reference = B256::repeat_byte(0x42).0;
+
+        let access_key =
+            derive_access_key(publisher.to_bytes().0, grantee.credential().verifying_key())
+                .unwrap();
+        let wrapped = wrap_key(&access_key, &reference);
+        assert_ne!(wrapped, reference);
+
+        let access_key =
+            derive_access_key(grantee.to_bytes().0, publisher.credential().verifying_key())
+                .unwrap();
+        assert_eq!(unwrap_key(&access_key, &wrapped), reference);
+    }
+
+    #[test]
+    fn rejects_invalid_root_key() {
+        let publisher = PrivateKeySigner::random();
+        assert_eq!(
+            derive_access_key([0; 32], publisher.credential().verifying_key()),
+            Err(AccessError::InvalidRootKey)
+        );
+    }
+}
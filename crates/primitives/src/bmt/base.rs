@@ -0,0 +1,76 @@
+use alloy::primitives::Keccak256;
+use nectar_primitives_traits::{Segment, SEGMENT_SIZE};
+
+use crate::bmt::{zero_hashes::ZERO_HASHES, DEPTH};
+
+/// The hash function a BMT is built with, compressing each pair of nodes into their parent and
+/// the span with the tree root into the chunk address.
+///
+/// Swarm addresses are built with [`Keccak256`], which is the default for
+/// [`Hasher`](crate::bmt::Hasher). Other hash functions yield different, non-Swarm addresses,
+/// eg. for benchmarking the tree construction against them.
+pub trait BaseHasher: Send + Sync + 'static {
+    /// Create a hasher with empty state.
+    fn new() -> Self;
+
+    /// Absorb `data` into the state of the hasher.
+    fn update(&mut self, data: &[u8]);
+
+    /// Write the 32 byte digest of the absorbed data into `output`.
+    fn finalize_into(self, output: &mut [u8]);
+
+    /// The hash of an all zero subtree at each level of the BMT, starting from a zero segment,
+    /// used to pad the tree of data shorter than a chunk.
+    fn zero_hashes() -> &'static [Segment; DEPTH];
+}
+
+/// Compute the zero subtree hashes of a BMT for the base hasher `H`.
+pub fn compute_zero_hashes<H: BaseHasher>() -> [Segment; DEPTH] {
+    let mut zero_hashes = [[0u8; SEGMENT_SIZE]; DEPTH];
+    for i in 1..DEPTH {
+        let mut hasher = H::new();
+        hasher.update(&zero_hashes[i - 1]);
+        hasher.update(&zero_hashes[i - 1]);
+        hasher.finalize_into(&mut zero_hashes[i]);
+    }
+
+    zero_hashes
+}
+
+impl BaseHasher for Keccak256 {
+    fn new() -> Self {
+        Keccak256::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        Keccak256::update(self, data)
+    }
+
+    fn finalize_into(self, output: &mut [u8]) {
+        Keccak256::finalize_into(self, output)
+    }
+
+    fn zero_hashes() -> &'static [Segment; DEPTH] {
+        &ZERO_HASHES
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl BaseHasher for blake3::Hasher {
+    fn new() -> Self {
+        blake3::Hasher::new()
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        blake3::Hasher::update(self, data);
+    }
+
+    fn finalize_into(self, output: &mut [u8]) {
+        output.copy_from_slice(self.finalize().as_bytes());
+    }
+
+    fn zero_hashes() -> &'static [Segment; DEPTH] {
+        static ZERO_HASHES: std::sync::OnceLock<[Segment; DEPTH]> = std::sync::OnceLock::new();
+        ZERO_HASHES.get_or_init(compute_zero_hashes::<Self>)
+    }
+}
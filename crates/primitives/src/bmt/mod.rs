@@ -1,38 +1,54 @@
 use alloy::primitives::Keccak256;
 use anyhow::Result;
 use nectar_primitives_traits::{Segment, Span, CHUNK_SIZE, SEGMENT_SIZE};
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+    marker::PhantomData,
+    sync::{atomic::Ordering, Arc},
+};
 use thiserror::Error;
 use tree::{Tree, TreeIterator};
 
+mod base;
 mod pool;
 mod proof;
 mod reference;
 mod tree;
 mod zero_hashes;
-use zero_hashes::ZERO_HASHES;
 
+pub use base::{compute_zero_hashes, BaseHasher};
 pub use pool::*;
 pub use proof::*;
 pub use reference::RefHasher;
 pub use tree::DEPTH;
 
+/// A BMT hasher over the base hash function `H`, which is keccak256 for Swarm addresses.
 #[derive(Debug)]
-pub struct Hasher {
+pub struct Hasher<H = Keccak256> {
     pool: Option<Arc<Pool>>,
     tree: Arc<Tree>,
     size: usize,
     pos: usize,
     span: Span,
+    base: PhantomData<H>,
 }
 
-unsafe impl Send for Hasher {}
-unsafe impl Sync for Hasher {}
+unsafe impl<H> Send for Hasher<H> {}
+unsafe impl<H> Sync for Hasher<H> {}
 
-#[derive(Default)]
-pub struct HasherBuilder {
+pub struct HasherBuilder<H = Keccak256> {
     pool: Option<Arc<Pool>>,
     tree: Option<Arc<Tree>>,
+    base: PhantomData<H>,
+}
+
+impl Default for HasherBuilder {
+    fn default() -> Self {
+        Self {
+            pool: None,
+            tree: None,
+            base: PhantomData,
+        }
+    }
 }
 
 impl HasherBuilder {
@@ -40,6 +56,18 @@ impl HasherBuilder {
     pub fn new() -> Self {
         Self::default()
     }
+}
+
+impl<H: BaseHasher> HasherBuilder<H> {
+    /// Build the BMT with the base hash function `B` in place of keccak256. The resulting
+    /// addresses are not Swarm addresses.
+    pub fn with_base_hasher<B: BaseHasher>(self) -> HasherBuilder<B> {
+        HasherBuilder {
+            pool: self.pool,
+            tree: self.tree,
+            base: PhantomData,
+        }
+    }
 
     /// Populate the builder with configuration from the respective pool..
     pub fn with_pool(mut self, pool: Arc<Pool>) -> Self {
@@ -55,7 +83,7 @@ impl HasherBuilder {
     }
 
     /// Given the state of the builder, construct a [`Hasher`].
-    pub fn build(self) -> Result<Hasher, HashError> {
+    pub fn build(self) -> Result<Hasher<H>, HashError> {
         let tree = self.tree.unwrap_or(Arc::new(Tree::new()));
 
         Ok(Hasher {
@@ -64,6 +92,7 @@ impl HasherBuilder {
             pos: 0,
             span: 0,
             pool: self.pool,
+            base: PhantomData,
         })
     }
 }
@@ -74,11 +103,11 @@ pub enum HashError {
     InvalidLength(u64),
 }
 
-impl Hasher {
+impl<H: BaseHasher> Hasher<H> {
     #[inline(always)]
     pub fn hash(&mut self, output: &mut [u8]) {
         if self.size == 0 {
-            return self.root_hash(ZERO_HASHES.last().unwrap(), output);
+            return self.root_hash(H::zero_hashes().last().unwrap(), output);
         }
 
         // write the last section with final flag set to true
@@ -137,14 +166,15 @@ impl Hasher {
 
         for (current_node, (left, right), parent_state, level, _) in tree_iterator {
             // If `is_final` and `right` is zero, replace `right` with the precomputed zero hash
-            if is_final && right == &ZERO_HASHES[0] {
-                right.copy_from_slice(&ZERO_HASHES[level - 1]);
+            let zero_hashes = H::zero_hashes();
+            if is_final && right == &zero_hashes[0] {
+                right.copy_from_slice(&zero_hashes[level - 1]);
             }
 
-            let mut hasher = Keccak256::new();
+            let mut hasher = H::new();
             hasher.update(left);
             hasher.update(right);
-            hasher.finalize_into_array(current_node);
+            hasher.finalize_into(current_node);
 
             // Handle concurrency when not finalising.
             if is_final && parent_state.is_none() {
@@ -170,7 +200,7 @@ impl Hasher {
 
     #[inline(always)]
     fn root_hash(&self, last: &[u8], output: &mut [u8]) {
-        let mut hasher = Keccak256::new();
+        let mut hasher = H::new();
         hasher.update(&self.span.to_le_bytes());
         hasher.update(last);
 
@@ -178,7 +208,7 @@ impl Hasher {
     }
 }
 
-impl Drop for Hasher {
+impl<H> Drop for Hasher<H> {
     fn drop(&mut self) {
         if let Some(pool) = &self.pool {
             let pool = pool.clone();
@@ -332,4 +362,62 @@ mod tests {
 
         join_all(handles).await;
     }
+
+    fn hash_with<H: BaseHasher>(data: &[u8]) -> Segment {
+        let mut hasher = HasherBuilder::new()
+            .with_base_hasher::<H>()
+            .build()
+            .unwrap();
+        hasher.set_span(data.len() as u64);
+        hasher.write(data).unwrap();
+
+        let mut segment: Segment = [0u8; 32];
+        hasher.hash(&mut segment);
+        segment
+    }
+
+    #[test]
+    fn test_keccak_base_hasher() {
+        assert_eq!(compute_zero_hashes::<Keccak256>(), zero_hashes::ZERO_HASHES);
+
+        // an explicit keccak base hasher gives the same addresses as the default
+        assert_eq!(
+            hash_with::<Keccak256>(&[1, 2, 3]),
+            b256!("ca6357a08e317d15ec560fef34e4c45f8f19f01c372aa70f1da72bfa7f1a4338")
+        );
+
+        let (_, data, msg) = rand_data::<CHUNK_SIZE>();
+        for len in [0, 31, 64, 1000, CHUNK_SIZE] {
+            assert_eq!(
+                hash_with::<Keccak256>(&data[..len]),
+                ref_hash(&data[..len]),
+                "{msg}"
+            );
+        }
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3_base_hasher() {
+        let (_, data, msg) = rand_data::<CHUNK_SIZE>();
+
+        for len in [0, 3, 64, 1000, CHUNK_SIZE] {
+            let data = &data[..len];
+            let hash = hash_with::<blake3::Hasher>(data);
+
+            assert_eq!(hash, hash_with::<blake3::Hasher>(data), "{msg}");
+            assert_ne!(hash, hash_with::<Keccak256>(data), "{msg}");
+        }
+
+        // the span is folded into the root as with keccak
+        let mut hasher = HasherBuilder::new()
+            .with_base_hasher::<blake3::Hasher>()
+            .build()
+            .unwrap();
+        hasher.set_span(4);
+        hasher.write(&[1, 2, 3]).unwrap();
+        let mut segment: Segment = [0u8; 32];
+        hasher.hash(&mut segment);
+        assert_ne!(segment, hash_with::<blake3::Hasher>(&[1, 2, 3]));
+    }
 }
@@ -95,12 +95,10 @@ impl Prover for Hasher {
         let mut proof_segments: [Segment; PROOF_LENGTH] = [[0u8; SEGMENT_SIZE]; PROOF_LENGTH];
         proof_segments[0] = *first_proof_segment;
 
-        let mut proof_index = 1;
         let mut from_left = index % 2 == 0;
-        for (_, (left, right), _, _, index) in tree_iterator {
+        for (proof_index, (_, (left, right), _, _, index)) in (1..).zip(tree_iterator) {
             proof_segments[proof_index] = if from_left { *right } else { *left };
 
-            proof_index += 1;
             from_left = index % 2 == 0;
         }
 
@@ -150,8 +148,8 @@ impl Prover for Hasher {
 
         // Combine the final hash with the span to compute the root hash
         let mut hasher = Keccak256::new();
-        hasher.update(&proof.span.to_le_bytes());
-        hasher.update(&current_hash);
+        hasher.update(proof.span.to_le_bytes());
+        hasher.update(current_hash);
 
         let mut root_hash: Segment = [0u8; SEGMENT_SIZE];
         hasher.finalize_into(&mut root_hash);
@@ -177,7 +175,7 @@ mod tests {
 
         let pool = Arc::new(Pool::new(1).await);
         let mut prover = pool.get_hasher().await.unwrap();
-        let _ = prover.set_span(buf.len() as u64);
+        prover.set_span(buf.len() as u64);
         prover.write(&buf).unwrap();
 
         let mut output = [0u8; SEGMENT_SIZE];
@@ -303,7 +301,7 @@ mod tests {
 
         let pool = Arc::new(Pool::new(1).await);
         let mut prover = pool.get_hasher().await.unwrap();
-        let _ = prover.set_span(buf.len() as u64);
+        prover.set_span(buf.len() as u64);
         prover.write(&buf).unwrap();
 
         let proof = Proof {
@@ -335,7 +333,7 @@ mod tests {
         // Create a BMT pool
         let pool = Arc::new(Pool::new(1).await);
         let mut hasher = pool.get_hasher().await.unwrap();
-        let _ = hasher.set_span(buf.len().try_into().unwrap());
+        hasher.set_span(buf.len().try_into().unwrap());
         hasher.write(&buf).unwrap();
 
         let mut root_hash = [0u8; SEGMENT_SIZE];
@@ -1,6 +1,5 @@
 use alloy::primitives::keccak256;
 use nectar_primitives_traits::SEGMENT_SIZE;
-use rayon::prelude::*;
 use std::cell::RefCell;
 use thread_local::ThreadLocal;
 
@@ -10,6 +9,12 @@ pub struct RefHasher<const N: usize> {
     buffer_pool: ThreadLocal<RefCell<Vec<u8>>>,
 }
 
+impl<const N: usize> Default for RefHasher<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<const N: usize> RefHasher<N> {
     pub fn new() -> Self {
         let mut c = 2;
@@ -46,8 +46,8 @@ const LEAVES_LEVEL_OFFSETS: [usize; LEAVES_DEPTH] = generate_offset_table::<LEAV
 /// DEPTH >= 2. Reasoning for omitting levels:
 /// * Level 0: Omitted as these nodes are not the parent of any other node.
 /// * Level 1: Omitted as these nodes are the parents of a pair of level 0 nodes, however there is
-/// no need to evaluate the state whether or not it has been hashed as this represents the initial
-/// level from which the algorithm starts at.
+///   no need to evaluate the state whether or not it has been hashed as this represents the
+///   initial level from which the algorithm starts at.
 const STATE_DEPTH: usize = DEPTH - 2;
 const STATE_CAPACITY: usize = (1 << (STATE_DEPTH)) - 1;
 const STATE_LEVEL_OFFSETS: [usize; STATE_DEPTH] = generate_offset_table::<STATE_DEPTH>();
@@ -87,16 +87,18 @@ impl Tree {
     pub(crate) fn get_buf_segment(&self, offset: usize) -> &Segment {
         unsafe {
             // SAFETY: The caller ensures the offset and size are valid.
-            &*(&(*self.buf.get())[offset..offset + SEGMENT_SIZE] as *const [u8] as *const Segment)
+            &*(&(&(*self.buf.get()))[offset..offset + SEGMENT_SIZE] as *const [u8]
+                as *const Segment)
         }
     }
 
     /// Safely get a mutable reference to a segment in the buffer
+    #[allow(clippy::mut_from_ref)]
     pub(crate) fn get_buf_segment_mut(&self, offset: usize) -> &mut Segment {
         unsafe {
             // SAFETY: The caller ensures the offset and size are valid, and that no other mutable
             // or immutable references exist to this region.
-            &mut *(&mut (*self.buf.get())[offset..offset + SEGMENT_SIZE] as *mut [u8]
+            &mut *(&mut (&mut (*self.buf.get()))[offset..offset + SEGMENT_SIZE] as *mut [u8]
                 as *mut Segment)
         }
     }
@@ -110,6 +112,7 @@ impl Tree {
     }
 
     /// Safely get a mutable reference to a leaf segment.
+    #[allow(clippy::mut_from_ref)]
     pub(crate) fn get_leaf_mut(&self, index: usize) -> &mut Segment {
         unsafe {
             // SAFETY: The caller ensures that `index is valid, and no other mutable or immutable
@@ -119,6 +122,7 @@ impl Tree {
     }
 
     /// Safely get a mutable reference to a state node.
+    #[allow(clippy::mut_from_ref)]
     pub(crate) fn get_state_mut(&self, index: usize) -> &mut AtomicBool {
         unsafe {
             // SAFETY: The caller ensures that `index` is valid, and no other mutable or immutable
@@ -33,6 +33,15 @@ impl BMTBody {
         self.span
     }
 
+    /// Validates the body as a leaf chunk, whose span must equal the length of its data.
+    pub(crate) fn validate_leaf(&self) -> Result<()> {
+        if self.span != self.data.len() as Span {
+            return Err(ChunkError::span_mismatch(self.span, self.data.len()));
+        }
+
+        Ok(())
+    }
+
     /// Converts the body into its raw bytes representation
     // Internal method to compute the hash
     fn compute_hash(&self) -> ChunkAddress {
@@ -41,7 +50,9 @@ impl BMTBody {
             .expect("Failed to create hasher");
 
         hasher.set_span(self.span);
-        hasher.write(self.data.as_ref());
+        hasher
+            .write(self.data.as_ref())
+            .expect("Failed to write to hasher");
 
         let mut result = ChunkAddress::default();
         hasher.hash(result.as_mut());
@@ -62,7 +73,7 @@ impl ChunkData for BMTBody {
 impl ChunkBody for BMTBody {
     /// Returns the hash of the body, computing it if necessary
     fn hash(&self) -> ChunkAddress {
-        self.cached_hash.get_or_init(|| self.compute_hash()).clone()
+        *self.cached_hash.get_or_init(|| self.compute_hash())
     }
 }
 
@@ -2,7 +2,7 @@ use super::bmt_body::BMTBody;
 use bytes::Bytes;
 use nectar_primitives_traits::{
     chunk::{ChunkError, Result},
-    Chunk, ChunkAddress, ChunkBody, ChunkData,
+    Chunk, ChunkAddress, ChunkBody, ChunkData, BRANCHES, CHUNK_SIZE, SEGMENT_SIZE,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -34,6 +34,35 @@ impl ContentChunk {
     pub fn span(&self) -> u64 {
         self.body.span()
     }
+
+    /// Validates the chunk as a leaf, ie. that the span equals the length of the data
+    pub fn validate_leaf(&self) -> Result<()> {
+        self.body.validate_leaf()
+    }
+
+    /// Validates that the span is consistent with the data, eg. for chunks fetched from
+    /// untrusted peers. A span of up to [`CHUNK_SIZE`] is a leaf, whose span must equal the
+    /// length of its data. A larger span is an intermediate chunk, which must hold one plain
+    /// reference for every child subtree needed to cover the span.
+    pub fn verify_span(&self) -> Result<()> {
+        let span = self.span();
+        if span <= CHUNK_SIZE as u64 {
+            return self.validate_leaf();
+        }
+
+        // the size of the subtree below each reference
+        let mut child_span = CHUNK_SIZE as u64;
+        while child_span.saturating_mul(BRANCHES as u64) < span {
+            child_span *= BRANCHES as u64;
+        }
+
+        let data_len = self.data().len();
+        if span.div_ceil(child_span) * SEGMENT_SIZE as u64 != data_len as u64 {
+            return Err(ChunkError::span_mismatch(span, data_len));
+        }
+
+        Ok(())
+    }
 }
 
 impl ChunkData for ContentChunk {
@@ -159,6 +188,53 @@ mod tests {
         assert_eq!(<ContentChunk as Into<Bytes>>::into(chunk), data.as_slice());
     }
 
+    #[test]
+    fn test_validate_leaf() {
+        let data = b"greaterthanspan";
+        let chunk = ContentChunk::new(data.to_vec()).unwrap();
+        assert!(chunk.validate_leaf().is_ok());
+
+        let chunk = ContentChunk::new_with_span(42, data.to_vec()).unwrap();
+        assert!(matches!(
+            chunk.validate_leaf(),
+            Err(ChunkError::SpanMismatch { span: 42, data_len }) if data_len == data.len()
+        ));
+    }
+
+    #[test]
+    fn test_verify_span() {
+        let data = b"greaterthanspan";
+        assert!(ContentChunk::new(data.to_vec())
+            .unwrap()
+            .verify_span()
+            .is_ok());
+
+        // a forged leaf span
+        let chunk = ContentChunk::new_with_span(42, data.to_vec()).unwrap();
+        assert!(matches!(
+            chunk.verify_span(),
+            Err(ChunkError::SpanMismatch { span: 42, .. })
+        ));
+
+        // two leaves, the second partial
+        let chunk = ContentChunk::new_with_span(CHUNK_SIZE as u64 + 1, vec![0; 64]).unwrap();
+        assert!(chunk.verify_span().is_ok());
+
+        // a full intermediate chunk of leaves, and one with a carried leaf one level up
+        let full = (CHUNK_SIZE * BRANCHES) as u64;
+        let chunk = ContentChunk::new_with_span(full, vec![0; CHUNK_SIZE]).unwrap();
+        assert!(chunk.verify_span().is_ok());
+        let chunk = ContentChunk::new_with_span(full + 1, vec![0; 64]).unwrap();
+        assert!(chunk.verify_span().is_ok());
+
+        // an intermediate span claiming more data than its references cover
+        let chunk = ContentChunk::new_with_span(3 * CHUNK_SIZE as u64, vec![0; 64]).unwrap();
+        assert!(matches!(
+            chunk.verify_span(),
+            Err(ChunkError::SpanMismatch { data_len: 64, .. })
+        ));
+    }
+
     #[test]
     fn test_size_validation() {
         let result = ContentChunk::new(vec![0; CHUNK_SIZE + 1]);
@@ -0,0 +1,95 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use nectar_primitives_traits::{
+    chunk::{ChunkError, Result},
+    Chunk, CHUNK_SIZE,
+};
+
+use super::ContentChunk;
+use crate::{proximity::proximity, MAX_PO};
+
+/// Size of the nonce prefixed to the payload of a mined chunk
+pub const NONCE_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Maximum number of nonces tried by [`mine_chunk`]
+pub const MAX_MINING_ATTEMPTS: u32 = 1 << 20;
+
+/// Mine a content chunk whose address shares its first `po` bits with `target_prefix`, so that it
+/// is stored within the target neighbourhood.
+///
+/// The chunk data is a little-endian `u32` nonce followed by `payload`, and nonces are tried in
+/// turn until the address falls within the neighbourhood. `po` is capped by the length of the
+/// prefix and by the maximum proximity order of 31. Returns the chunk and the nonce found, or `None` if none was found within
+/// [`MAX_MINING_ATTEMPTS`].
+pub fn mine_chunk(
+    payload: &[u8],
+    target_prefix: &[u8],
+    po: u8,
+) -> Result<Option<(ContentChunk, u32)>> {
+    if NONCE_SIZE + payload.len() > CHUNK_SIZE {
+        return Err(ChunkError::size(
+            "mined payload too large",
+            NONCE_SIZE + payload.len(),
+            CHUNK_SIZE,
+        ));
+    }
+
+    // proximity never reports more than MAX_PO, so a deeper po could never be reached
+    let po = (po as usize).min(target_prefix.len() * 8).min(MAX_PO) as u8;
+
+    let mut data = BytesMut::with_capacity(NONCE_SIZE + payload.len());
+    for nonce in 0..MAX_MINING_ATTEMPTS {
+        data.clear();
+        data.put_u32_le(nonce);
+        data.put_slice(payload);
+
+        let chunk = ContentChunk::new(Bytes::copy_from_slice(&data))?;
+        if proximity(chunk.address().as_slice(), target_prefix) >= po {
+            return Ok(Some((chunk, nonce)));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nectar_primitives_traits::ChunkData;
+
+    #[test]
+    fn mine_to_prefix() {
+        let payload = b"a message for the neighbourhood";
+
+        for (prefix, po) in [(0xa5u8, 8u8), (0x3c, 4)] {
+            let (chunk, nonce) = mine_chunk(payload, &[prefix], po).unwrap().unwrap();
+
+            let address = chunk.address();
+            assert_eq!(address[0] >> (8 - po), prefix >> (8 - po));
+            assert_eq!(&chunk.data()[..NONCE_SIZE], nonce.to_le_bytes());
+            assert_eq!(&chunk.data()[NONCE_SIZE..], payload);
+        }
+    }
+
+    #[test]
+    fn po_is_capped_by_prefix() {
+        let (chunk, _) = mine_chunk(b"foo", &[0xff], 31).unwrap().unwrap();
+        assert_eq!(chunk.address()[0], 0xff);
+    }
+
+    #[test]
+    fn po_is_capped_by_max_po() {
+        let (chunk, nonce) = mine_chunk(b"foo", &[0xff], 8).unwrap().unwrap();
+
+        // even its own address is only MAX_PO close to the mined chunk
+        let mined = mine_chunk(b"foo", chunk.address().as_slice(), u8::MAX);
+        assert_eq!(mined.unwrap().unwrap().1, nonce);
+    }
+
+    #[test]
+    fn rejects_oversized_payload() {
+        assert!(matches!(
+            mine_chunk(&[0; CHUNK_SIZE], &[0], 1),
+            Err(ChunkError::Size { .. })
+        ));
+    }
+}
@@ -1,12 +1,197 @@
 mod bmt_body;
 mod content;
+mod mining;
 mod single_owner;
 
 pub use content::ContentChunk;
-pub use single_owner::SingleOwnerChunk;
+pub use mining::{mine_chunk, MAX_MINING_ATTEMPTS, NONCE_SIZE};
+pub use single_owner::{SingleOwnerChunk, SHARED_SOC_OWNER, SHARED_SOC_OWNER_PK};
 
-#[derive(Debug, Eq, PartialEq)]
+use bytes::Bytes;
+use nectar_primitives_traits::{
+    chunk::{ChunkError, Result},
+    Chunk as _, ChunkAddress, ChunkData,
+};
+
+use crate::postage::{PostageStamp, STAMP_SIZE};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Chunk {
     Content(ContentChunk),
     SingleOwner(SingleOwnerChunk),
 }
+
+impl Chunk {
+    /// Returns the address of the chunk, whichever its type
+    pub fn address(&self) -> ChunkAddress {
+        match self {
+            Chunk::Content(chunk) => chunk.address(),
+            Chunk::SingleOwner(chunk) => chunk.address(),
+        }
+    }
+}
+
+impl ChunkData for Chunk {
+    fn data(&self) -> &Bytes {
+        match self {
+            Chunk::Content(chunk) => chunk.data(),
+            Chunk::SingleOwner(chunk) => chunk.data(),
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            Chunk::Content(chunk) => chunk.size(),
+            Chunk::SingleOwner(chunk) => chunk.size(),
+        }
+    }
+}
+
+impl nectar_primitives_traits::Chunk for Chunk {
+    fn address(&self) -> ChunkAddress {
+        Chunk::address(self)
+    }
+}
+
+/// Decode a chunk as received over the wire, optionally followed by its 113 byte postage stamp.
+///
+/// Neither the type of the chunk nor the presence of a stamp can be told from the encoding
+/// alone, so as in Bee the chunk is decoded as whichever type hashes to `address`: first the
+/// whole of `bytes` as a content or single owner chunk, then everything before a trailing
+/// stamp. The stamp is only parsed, not validated against the chunk or its batch.
+pub fn decode_stamped(
+    bytes: &[u8],
+    address: ChunkAddress,
+) -> Result<(Chunk, Option<PostageStamp>)> {
+    if let Some(chunk) = decode_at(bytes, address) {
+        return Ok((chunk, None));
+    }
+
+    if bytes.len() > STAMP_SIZE {
+        let (body, stamp) = bytes.split_at(bytes.len() - STAMP_SIZE);
+        if let Some(chunk) = decode_at(body, address) {
+            let stamp =
+                PostageStamp::from_bytes(stamp).map_err(|_| ChunkError::format("invalid stamp"))?;
+            return Ok((chunk, Some(stamp)));
+        }
+    }
+
+    Err(ChunkError::format("no chunk with the expected address"))
+}
+
+fn decode_at(bytes: &[u8], address: ChunkAddress) -> Option<Chunk> {
+    if let Ok(chunk) = ContentChunk::try_from(bytes) {
+        if chunk.address() == address {
+            return Some(Chunk::Content(chunk));
+        }
+    }
+
+    SingleOwnerChunk::try_from(bytes)
+        .ok()
+        .filter(|chunk| chunk.verify(address).is_ok())
+        .map(Chunk::SingleOwner)
+}
+
+impl From<Chunk> for Bytes {
+    fn from(chunk: Chunk) -> Self {
+        match chunk {
+            Chunk::Content(chunk) => chunk.into(),
+            Chunk::SingleOwner(chunk) => chunk.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::postage::digest;
+    use alloy::{
+        primitives::B256,
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+
+    /// Flip every byte of a valid encoding in turn and assert that the tampered encoding is
+    /// either rejected by the decoder or decodes to a chunk that fails verification against the
+    /// original address.
+    fn assert_tamper_evident<T: nectar_primitives_traits::Chunk>(
+        encoded: &[u8],
+        expected: ChunkAddress,
+        decode: impl Fn(&[u8]) -> Result<T>,
+    ) {
+        decode(encoded).unwrap().verify(expected).unwrap();
+
+        for i in 0..encoded.len() {
+            let mut tampered = encoded.to_vec();
+            tampered[i] ^= 0xff;
+
+            if let Ok(chunk) = decode(&tampered) {
+                assert!(
+                    chunk.verify(expected).is_err(),
+                    "tampering with byte {} went undetected",
+                    i
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn content_chunk_is_tamper_evident() {
+        for data in [b"foo".to_vec(), vec![0xab; 4096]] {
+            let chunk = ContentChunk::new(data).unwrap();
+            let address = chunk.address();
+            let encoded: Bytes = chunk.into();
+
+            assert_tamper_evident(&encoded, address, |b| ContentChunk::try_from(b));
+        }
+    }
+
+    #[tokio::test]
+    async fn single_owner_chunk_is_tamper_evident() {
+        let signer = PrivateKeySigner::random();
+        let chunk = SingleOwnerChunk::new(B256::repeat_byte(0x42), b"foo".to_vec(), signer)
+            .await
+            .unwrap();
+        let address = chunk.address();
+        let encoded: Bytes = chunk.into();
+
+        assert_tamper_evident(&encoded, address, |b| SingleOwnerChunk::try_from(b));
+    }
+
+    #[tokio::test]
+    async fn decode_stamped_round_trip() {
+        let signer = PrivateKeySigner::random();
+        let batch_id = B256::repeat_byte(0x01);
+
+        let content = ContentChunk::new(b"foo".to_vec()).unwrap();
+        let soc = SingleOwnerChunk::new(
+            B256::repeat_byte(0x42),
+            b"foo".to_vec(),
+            PrivateKeySigner::random(),
+        )
+        .await
+        .unwrap();
+
+        for chunk in [Chunk::Content(content), Chunk::SingleOwner(soc)] {
+            let address = chunk.address();
+            let signature = signer
+                .sign_message_sync(digest(address, batch_id, 0, 1).as_slice())
+                .unwrap();
+            let stamp = PostageStamp::new(batch_id, 0, 1, signature);
+            let encoded = Bytes::from(chunk.clone());
+
+            // without a stamp
+            let (decoded, decoded_stamp) = decode_stamped(&encoded, address).unwrap();
+            assert_eq!(decoded, chunk);
+            assert!(decoded_stamp.is_none());
+
+            // with a trailing stamp
+            let stamped = [encoded.as_ref(), stamp.to_bytes().as_slice()].concat();
+            let (decoded, decoded_stamp) = decode_stamped(&stamped, address).unwrap();
+            assert_eq!(decoded, chunk);
+            assert_eq!(decoded_stamp.unwrap().to_bytes(), stamp.to_bytes());
+
+            // nothing decodes to another address
+            assert!(decode_stamped(&stamped, B256::ZERO).is_err());
+        }
+    }
+}
@@ -21,6 +21,15 @@ pub const DISPERSED_REPLICA_OWNER: Address = address!("0xdc5b20847f43d67928f49cd
 pub const DISPERSED_REPLICA_OWNER_PK: B256 =
     b256!("0x0100000000000000000000000000000000000000000000000000000000000000");
 
+/// The address of the owner of shared SOCs, whose key is known to everyone writing to them.
+/// Generated from the private key `0x0200000000000000000000000000000000000000000000000000000000000000`.
+///
+/// This is a convention of this crate only. Bee's GSOC has no fixed owner, as its tooling mines
+/// a key per target neighbourhood, and Bee nodes attach no meaning to this address.
+pub const SHARED_SOC_OWNER: Address = address!("0x897df33a7b3c62ade01e22c13d48f98124b4480f");
+pub const SHARED_SOC_OWNER_PK: B256 =
+    b256!("0x0200000000000000000000000000000000000000000000000000000000000000");
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SingleOwnerChunk {
     id: B256,
@@ -77,7 +86,7 @@ impl SingleOwnerChunk {
         id[1..].copy_from_slice(&body.hash().as_slice()[1..]);
 
         let hash = Self::to_sign(id, &body);
-        let signer = PrivateKeySigner::from_slice(&DISPERSED_REPLICA_OWNER_PK.as_slice()).unwrap();
+        let signer = PrivateKeySigner::from_slice(DISPERSED_REPLICA_OWNER_PK.as_slice()).unwrap();
         let signature = signer.sign_message(hash.as_ref()).await?;
 
         Ok(Self {
@@ -88,11 +97,33 @@ impl SingleOwnerChunk {
         })
     }
 
+    /// Create a shared SOC, signed with the publicly known [`SHARED_SOC_OWNER_PK`] so that anyone
+    /// using this crate can write to the address of `id` under [`SHARED_SOC_OWNER`], eg. for
+    /// group owned feeds. The key is a convention of this crate, not a Bee GSOC key.
+    ///
+    /// As anyone holds the key, the signature of a shared SOC does not authenticate its writer:
+    /// its data must be verified by other means.
+    pub async fn new_shared(id: B256, data: impl Into<Bytes>) -> Result<Self> {
+        let signer = PrivateKeySigner::from_slice(SHARED_SOC_OWNER_PK.as_slice()).unwrap();
+        Self::new(id, data, signer).await
+    }
+
     /// Returns the ID of the chunk
     pub fn id(&self) -> B256 {
         self.id
     }
 
+    /// Serialises the chunk into a single buffer of exactly [`ChunkData::size`] bytes, without
+    /// consuming it or serialising the body separately.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(self.size());
+        bytes.extend_from_slice(self.id.as_ref());
+        bytes.extend_from_slice(&self.signature.as_bytes());
+        bytes.extend_from_slice(&self.body.span().to_le_bytes());
+        bytes.extend_from_slice(self.body.data().as_ref());
+        bytes.freeze()
+    }
+
     fn to_sign(id: B256, body: &impl ChunkBody) -> B256 {
         let mut hasher = Keccak256::new();
         hasher.update(id);
@@ -146,7 +177,7 @@ impl Signable for SingleOwnerChunk {
         *self.cached_owner.get_or_init(|| {
             let hash = Self::to_sign(self.id, &self.body);
             self.signature
-                .recover_address_from_msg(&hash)
+                .recover_address_from_msg(hash)
                 .unwrap_or(Address::ZERO)
         })
     }
@@ -156,8 +187,14 @@ impl Signable for SingleOwnerChunk {
     }
 
     fn verify_signature(&self) -> Result<()> {
+        // owner() reads an unrecoverable signature as the zero address, so recover here to
+        // surface the error
+        let hash = Self::to_sign(self.id, &self.body);
+        let owner = self.signature.recover_address_from_msg(hash)?;
+        let _ = self.cached_owner.set(owner);
+
         // Dispersed replica check
-        if self.owner() == DISPERSED_REPLICA_OWNER && !self.is_valid_replica() {
+        if owner == DISPERSED_REPLICA_OWNER && !self.is_valid_replica() {
             return Err(ChunkError::Format("invalid dispersed replica"));
         }
 
@@ -167,11 +204,7 @@ impl Signable for SingleOwnerChunk {
 
 impl From<SingleOwnerChunk> for Bytes {
     fn from(chunk: SingleOwnerChunk) -> Self {
-        let mut bytes = BytesMut::with_capacity(chunk.size());
-        bytes.extend_from_slice(chunk.id.as_ref());
-        bytes.extend_from_slice(&chunk.signature().as_bytes());
-        bytes.extend_from_slice(Bytes::from(chunk.body).as_ref());
-        bytes.freeze()
+        chunk.to_bytes()
     }
 }
 
@@ -230,7 +263,7 @@ impl TryFrom<Bytes> for SingleOwnerChunk {
 
         let id = B256::from_slice(&bytes.split_to(ID_SIZE));
         let signature = PrimitiveSignature::try_from(bytes.split_to(SIGNATURE_SIZE).as_ref())
-            .map_err(|e| ChunkError::Signature(e))?;
+            .map_err(ChunkError::Signature)?;
 
         // bytes now contains only body data
         let body = BMTBody::try_from(bytes)?;
@@ -323,6 +356,26 @@ mod tests {
         assert_eq!(recovered_chunk.owner(), chunk.owner());
     }
 
+    #[tokio::test]
+    async fn test_to_bytes() {
+        let chunk = SingleOwnerChunk::new(B256::ZERO, b"foo".to_vec(), get_test_wallet())
+            .await
+            .unwrap();
+
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), chunk.size());
+
+        // id, signature and the body as it serialises on its own
+        let expected = [
+            chunk.id().as_slice(),
+            &chunk.signature().as_bytes(),
+            Bytes::from(chunk.body.clone()).as_ref(),
+        ]
+        .concat();
+        assert_eq!(bytes.as_ref(), expected.as_slice());
+        assert_eq!(Bytes::from(chunk), bytes);
+    }
+
     #[tokio::test]
     async fn test_invalid_data() {
         // Test insufficient data size
@@ -377,6 +430,23 @@ mod tests {
         assert_eq!(chunk.owner(), Address::ZERO);
     }
 
+    #[tokio::test]
+    async fn test_unrecoverable_signature_is_an_error() {
+        let invalid_signature = PrimitiveSignature::try_from([0u8; 65].as_slice()).unwrap();
+        let chunk =
+            SingleOwnerChunk::new_signed_unchecked(B256::ZERO, invalid_signature, b"test".to_vec())
+                .unwrap();
+
+        assert!(matches!(
+            chunk.verify_signature(),
+            Err(ChunkError::Signature(_))
+        ));
+        assert!(matches!(
+            chunk.verify(chunk.address()),
+            Err(ChunkError::Signature(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_invalid_chunk() {
         // Base valid data
@@ -429,6 +499,24 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_shared() {
+        let id = B256::repeat_byte(0x42);
+        let chunk = SingleOwnerChunk::new_shared(id, b"test data".to_vec())
+            .await
+            .unwrap();
+
+        assert_eq!(chunk.id(), id);
+        assert_eq!(chunk.owner(), SHARED_SOC_OWNER);
+        assert!(chunk.verify(chunk.address()).is_ok());
+
+        // the address only depends on the id, whoever writes the chunk
+        let other = SingleOwnerChunk::new_shared(id, b"other data".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(other.address(), chunk.address());
+    }
+
     #[tokio::test]
     async fn test_invalid_dispersed_replica() {
         let test_data = b"test data".to_vec();
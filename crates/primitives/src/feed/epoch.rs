@@ -0,0 +1,318 @@
+use alloy::{
+    primitives::{Address, Keccak256, B256},
+    signers::Signer,
+};
+use nectar_primitives_traits::{
+    chunk::{ChunkError, Result},
+    ChunkAddress, ChunkData, ChunkStore,
+};
+
+use super::{check_owner, fetch, soc_address};
+use crate::chunk::{Chunk, SingleOwnerChunk};
+
+/// Level of the root epoch, spanning the whole of time
+const MAX_LEVEL: u8 = 32;
+
+/// Size of the big-endian timestamp prefixed to the payload of an update
+const TIMESTAMP_SIZE: usize = std::mem::size_of::<u64>();
+
+/// A time range of length `2^level` starting at `start`, which is a multiple of the length.
+///
+/// Epochs form a binary tree rooted at the epoch of level 32 starting at 0, where each epoch is
+/// halved by its two children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Epoch {
+    pub start: u64,
+    pub level: u8,
+}
+
+impl Epoch {
+    /// The root epoch, at which the first update of a feed is published
+    pub const ROOT: Epoch = Epoch {
+        start: 0,
+        level: MAX_LEVEL,
+    };
+
+    pub fn new(start: u64, level: u8) -> Self {
+        Self { start, level }
+    }
+
+    /// Length of the epoch in seconds
+    pub fn length(&self) -> u64 {
+        1 << self.level
+    }
+
+    /// Whether `at` falls within the epoch
+    pub fn contains(&self, at: u64) -> bool {
+        at >= self.start && at - self.start < self.length()
+    }
+
+    /// The epoch one level up containing this one
+    pub fn parent(&self) -> Epoch {
+        let length = self.length() << 1;
+        Epoch::new(self.start / length * length, self.level + 1)
+    }
+
+    /// The child epoch containing `at`. An epoch of level 0 has no children and is returned as is.
+    pub fn child_at(&self, at: u64) -> Epoch {
+        if self.level == 0 {
+            return *self;
+        }
+
+        let level = self.level - 1;
+        let length = 1 << level;
+        let mut start = self.start;
+        if at & length > 0 {
+            start |= length;
+        }
+
+        Epoch::new(start, level)
+    }
+
+    /// The lowest common ancestor of the epochs containing `at` and `after`
+    pub fn lca(at: u64, after: u64) -> Epoch {
+        if after == 0 {
+            return Epoch::ROOT;
+        }
+
+        let diff = at.saturating_sub(after);
+        let mut length = 1u64;
+        let mut level = 0;
+        while level < MAX_LEVEL && (length < diff || at / length != after / length) {
+            length <<= 1;
+            level += 1;
+        }
+
+        Epoch::new(after / length * length, level)
+    }
+
+    /// The epoch to publish an update at `at` following the update published at this epoch at
+    /// time `last`
+    pub fn next(&self, last: u64, at: u64) -> Epoch {
+        if self.start + self.length() > at {
+            return self.child_at(at);
+        }
+
+        Epoch::lca(at, last).child_at(at)
+    }
+
+    /// The hash identifying the epoch, `keccak256(start || level)` with `start` big-endian
+    fn hash(&self) -> B256 {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.start.to_be_bytes());
+        hasher.update([self.level]);
+        hasher.finalize()
+    }
+}
+
+/// A feed whose updates are published as single owner chunks at epochs, so that the update
+/// valid at a given time can be found by descending the epoch tree.
+///
+/// As in Bee, the first update is published at the root epoch and each following update at the
+/// epoch after the previous one, found from the epoch and the time of the previous update, see
+/// [`Epoch::next`]. The payload of an update is prefixed with the big-endian timestamp it was
+/// published at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochFeed {
+    pub owner: Address,
+    pub topic: B256,
+    // the epoch and time of the last update
+    last: Option<(Epoch, u64)>,
+}
+
+impl EpochFeed {
+    pub fn new(owner: Address, topic: B256) -> Self {
+        Self {
+            owner,
+            topic,
+            last: None,
+        }
+    }
+
+    /// Continue a feed whose last update was published at `epoch` at time `at`
+    pub fn with_last(mut self, epoch: Epoch, at: u64) -> Self {
+        self.last = Some((epoch, at));
+        self
+    }
+
+    /// The epoch of the last update published through this feed
+    pub fn last(&self) -> Option<Epoch> {
+        self.last.map(|(epoch, _)| epoch)
+    }
+
+    /// The single owner chunk id of the update at `epoch`, `keccak256(topic || epoch hash)`
+    pub fn id_at(&self, epoch: Epoch) -> B256 {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.topic);
+        hasher.update(epoch.hash());
+        hasher.finalize()
+    }
+
+    /// The chunk address of the update at `epoch`
+    pub fn address_at(&self, epoch: Epoch) -> ChunkAddress {
+        soc_address(self.id_at(epoch), self.owner)
+    }
+
+    /// The epoch the next update, published at time `at`, goes to
+    pub fn next_epoch(&self, at: u64) -> Epoch {
+        match self.last {
+            Some((epoch, last)) => epoch.next(last, at),
+            None => Epoch::ROOT,
+        }
+    }
+
+    /// Sign `payload` as the update published at time `at`. The signer must be the feed owner.
+    pub async fn update(
+        &mut self,
+        signer: impl Signer + Send + Sync,
+        at: u64,
+        payload: &[u8],
+    ) -> Result<SingleOwnerChunk> {
+        check_owner(self.owner, signer.address())?;
+
+        let epoch = self.next_epoch(at);
+        let data = [&at.to_be_bytes()[..], payload].concat();
+        let chunk = SingleOwnerChunk::new(self.id_at(epoch), data, signer).await?;
+
+        self.last = Some((epoch, at));
+        Ok(chunk)
+    }
+
+    /// Find the update valid at time `at` in `store`, that is the latest one published no later
+    /// than `at`, returning its epoch and chunk.
+    ///
+    /// Starting at the root epoch, the descent follows the child epochs containing `at` for as
+    /// long as they hold an update published no later than `at`.
+    pub async fn find_at(
+        &self,
+        store: &impl ChunkStore<Chunk = Chunk>,
+        at: u64,
+    ) -> Result<Option<(Epoch, SingleOwnerChunk)>> {
+        let mut found = None;
+        let mut epoch = Epoch::ROOT;
+
+        while let Some(chunk) = fetch(store, self.address_at(epoch)).await? {
+            if Self::timestamp(&chunk)? > at {
+                break;
+            }
+
+            found = Some((epoch, chunk));
+            if epoch.level == 0 {
+                break;
+            }
+            epoch = epoch.child_at(at);
+        }
+
+        Ok(found)
+    }
+
+    /// The time an update was published at
+    pub fn timestamp(chunk: &SingleOwnerChunk) -> Result<u64> {
+        let data = chunk.data();
+        if data.len() < TIMESTAMP_SIZE {
+            return Err(ChunkError::format("feed update is missing its timestamp"));
+        }
+
+        Ok(u64::from_be_bytes(
+            data[..TIMESTAMP_SIZE].try_into().unwrap(),
+        ))
+    }
+
+    /// The payload of an update, without its timestamp
+    pub fn payload(chunk: &SingleOwnerChunk) -> &[u8] {
+        &chunk.data()[TIMESTAMP_SIZE.min(chunk.data().len())..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryChunkStore;
+    use alloy::signers::local::PrivateKeySigner;
+    use nectar_primitives_traits::{Chunk as _, Signable};
+
+    #[test]
+    fn epoch_tree() {
+        assert_eq!(Epoch::ROOT.length(), 1 << 32);
+        assert_eq!(Epoch::ROOT.child_at(20), Epoch::new(0, 31));
+        assert_eq!(Epoch::ROOT.child_at(1 << 31), Epoch::new(1 << 31, 31));
+        assert_eq!(Epoch::new(16, 4).child_at(20), Epoch::new(16, 3));
+        assert_eq!(Epoch::new(16, 4).child_at(27), Epoch::new(24, 3));
+        assert_eq!(Epoch::new(24, 3).parent(), Epoch::new(16, 4));
+        assert_eq!(Epoch::new(0, 31).parent(), Epoch::ROOT);
+
+        assert_eq!(Epoch::lca(20, 0), Epoch::ROOT);
+        assert_eq!(Epoch::lca(20, 17), Epoch::new(16, 3));
+        assert_eq!(Epoch::lca(40, 17), Epoch::new(0, 6));
+        assert!(Epoch::lca(1000, 100).contains(1000));
+        assert!(Epoch::lca(1000, 100).contains(100));
+
+        // the next epoch descends towards the time of the update
+        assert_eq!(Epoch::ROOT.next(10, 20), Epoch::new(0, 31));
+        assert_eq!(Epoch::new(16, 3).next(17, 20), Epoch::new(20, 2));
+        assert_eq!(Epoch::new(16, 3).next(17, 40), Epoch::new(32, 5));
+    }
+
+    #[test]
+    fn next_epoch_after_last_update() {
+        // past the end of an epoch starting at 0, the next epoch is found from the time of the
+        // last update rather than the start of its epoch, which would lead back to the root
+        let feed = EpochFeed::new(Address::ZERO, B256::ZERO).with_last(Epoch::new(0, 2), 3);
+        assert_eq!(Epoch::lca(5, 3), Epoch::new(0, 3));
+        assert_eq!(feed.next_epoch(5), Epoch::new(4, 2));
+
+        let feed = EpochFeed::new(Address::ZERO, B256::ZERO).with_last(Epoch::new(0, 4), 12);
+        assert_eq!(feed.next_epoch(40), Epoch::new(32, 5));
+    }
+
+    #[tokio::test]
+    async fn find_at() {
+        let signer = PrivateKeySigner::random();
+        let mut feed = EpochFeed::new(signer.address(), B256::repeat_byte(0x42));
+        let store = MemoryChunkStore::new();
+
+        assert!(feed.find_at(&store, 100).await.unwrap().is_none());
+
+        let mut epochs = Vec::new();
+        for at in [10u64, 20, 1000] {
+            let chunk = feed
+                .update(signer.clone(), at, format!("at {}", at).as_bytes())
+                .await
+                .unwrap();
+            let epoch = feed.last().unwrap();
+
+            assert_eq!(chunk.address(), feed.address_at(epoch));
+            assert_eq!(chunk.owner(), feed.owner);
+            assert!(epoch.contains(at));
+            store.put(Chunk::SingleOwner(chunk)).await.unwrap();
+            epochs.push(epoch);
+        }
+        assert_eq!(
+            epochs,
+            vec![Epoch::ROOT, Epoch::new(0, 31), Epoch::new(0, 30)]
+        );
+
+        for (at, expected) in [(15, 10), (20, 20), (25, 20), (999, 20), (5000, 1000)] {
+            let (epoch, chunk) = feed.find_at(&store, at).await.unwrap().unwrap();
+            assert_eq!(EpochFeed::timestamp(&chunk).unwrap(), expected);
+            assert_eq!(
+                EpochFeed::payload(&chunk),
+                format!("at {}", expected).as_bytes()
+            );
+            assert_eq!(chunk.address(), feed.address_at(epoch));
+        }
+
+        // nothing was published yet
+        assert!(feed.find_at(&store, 5).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_other_signer() {
+        let mut feed = EpochFeed::new(Address::ZERO, B256::ZERO);
+        assert!(matches!(
+            feed.update(PrivateKeySigner::random(), 0, b"foo").await,
+            Err(ChunkError::Verification { .. })
+        ));
+        assert_eq!(feed.last(), None);
+    }
+}
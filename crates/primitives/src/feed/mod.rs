@@ -0,0 +1,51 @@
+mod epoch;
+mod sequential;
+
+pub use epoch::{Epoch, EpochFeed};
+pub use sequential::SequentialFeed;
+
+use alloy::primitives::{Address, Keccak256, B256};
+use nectar_primitives_traits::{
+    chunk::{ChunkError, Result},
+    Chunk as _, ChunkAddress, ChunkStore,
+};
+
+use crate::chunk::{Chunk, SingleOwnerChunk};
+
+/// The address of the single owner chunk with `id` signed by `owner`
+fn soc_address(id: B256, owner: Address) -> ChunkAddress {
+    let mut hasher = Keccak256::new();
+    hasher.update(id);
+    hasher.update(owner);
+    hasher.finalize()
+}
+
+/// Check that updates are signed by the feed owner
+fn check_owner(owner: Address, signer: Address) -> Result<()> {
+    if signer != owner {
+        return Err(ChunkError::verification(
+            "signer is not the feed owner",
+            owner,
+            signer,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fetch and verify the feed update at `address`, returning `None` if it isn't stored
+async fn fetch(
+    store: &impl ChunkStore<Chunk = Chunk>,
+    address: ChunkAddress,
+) -> Result<Option<SingleOwnerChunk>> {
+    match store.get(address).await? {
+        Some(Chunk::SingleOwner(chunk)) => {
+            chunk.verify(address)?;
+            Ok(Some(chunk))
+        }
+        Some(Chunk::Content(_)) => Err(ChunkError::format(
+            "feed update is not a single owner chunk",
+        )),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,153 @@
+use alloy::{
+    primitives::{Address, Keccak256, B256},
+    signers::Signer,
+};
+use bytes::Bytes;
+use nectar_primitives_traits::{chunk::Result, ChunkAddress, ChunkStore};
+
+use super::{check_owner, fetch, soc_address};
+use crate::chunk::{Chunk, SingleOwnerChunk};
+
+/// A feed whose updates are published as single owner chunks at consecutive indices.
+///
+/// The update at `index` is signed by `owner` with the id `keccak256(topic || index)`, where the
+/// index is big-endian encoded, so anyone knowing the owner and topic can find every update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequentialFeed {
+    pub owner: Address,
+    pub topic: B256,
+}
+
+impl SequentialFeed {
+    pub fn new(owner: Address, topic: B256) -> Self {
+        Self { owner, topic }
+    }
+
+    /// The single owner chunk id of the update at `index`
+    pub fn id_at(&self, index: u64) -> B256 {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.topic);
+        hasher.update(index.to_be_bytes());
+        hasher.finalize()
+    }
+
+    /// The chunk address of the update at `index`
+    pub fn address_at(&self, index: u64) -> ChunkAddress {
+        soc_address(self.id_at(index), self.owner)
+    }
+
+    /// Sign `payload` as the update at `index`. The signer must be the feed owner.
+    pub async fn update(
+        &self,
+        signer: impl Signer + Send + Sync,
+        index: u64,
+        payload: impl Into<Bytes>,
+    ) -> Result<SingleOwnerChunk> {
+        check_owner(self.owner, signer.address())?;
+
+        SingleOwnerChunk::new(self.id_at(index), payload, signer).await
+    }
+
+    /// Find the latest update in `store`, returning its index and chunk, or `None` if the feed
+    /// has no updates.
+    ///
+    /// Updates are assumed to be published without gaps. Indices are probed at exponentially
+    /// growing steps until one is missing, then the range between the last update found and the
+    /// missing index is bisected, so only a logarithmic number of chunks are fetched.
+    pub async fn find_latest(
+        &self,
+        store: &impl ChunkStore<Chunk = Chunk>,
+    ) -> Result<Option<(u64, SingleOwnerChunk)>> {
+        let mut latest = match fetch(store, self.address_at(0)).await? {
+            Some(chunk) => (0, chunk),
+            None => return Ok(None),
+        };
+
+        let mut missing = 1;
+        loop {
+            match fetch(store, self.address_at(missing)).await? {
+                Some(chunk) if missing == u64::MAX => return Ok(Some((missing, chunk))),
+                Some(chunk) => {
+                    latest = (missing, chunk);
+                    missing = missing.saturating_mul(2);
+                }
+                None => break,
+            }
+        }
+
+        while missing - latest.0 > 1 {
+            let index = latest.0 + (missing - latest.0) / 2;
+            match fetch(store, self.address_at(index)).await? {
+                Some(chunk) => latest = (index, chunk),
+                None => missing = index,
+            }
+        }
+
+        Ok(Some(latest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryChunkStore;
+    use alloy::signers::local::PrivateKeySigner;
+    use nectar_primitives_traits::{chunk::ChunkError, Chunk as _, ChunkData, Signable};
+
+    #[tokio::test]
+    async fn sequential_updates() {
+        let signer = PrivateKeySigner::random();
+        let feed = SequentialFeed::new(signer.address(), B256::repeat_byte(0x42));
+
+        for index in 0..3u64 {
+            let payload = format!("update {}", index);
+            let chunk = feed
+                .update(signer.clone(), index, payload.clone().into_bytes())
+                .await
+                .unwrap();
+
+            assert_eq!(chunk.id(), feed.id_at(index));
+            assert_eq!(chunk.address(), feed.address_at(index));
+            assert_eq!(chunk.owner(), feed.owner);
+            assert_eq!(chunk.data().as_ref(), payload.as_bytes());
+            chunk.verify(feed.address_at(index)).unwrap();
+        }
+
+        assert_ne!(feed.id_at(0), feed.id_at(1));
+    }
+
+    #[tokio::test]
+    async fn find_latest() {
+        let signer = PrivateKeySigner::random();
+        let feed = SequentialFeed::new(signer.address(), B256::repeat_byte(0x42));
+        let store = MemoryChunkStore::new();
+
+        assert!(feed.find_latest(&store).await.unwrap().is_none());
+
+        for index in 0..=5u64 {
+            let chunk = feed
+                .update(signer.clone(), index, index.to_be_bytes().to_vec())
+                .await
+                .unwrap();
+            store.put(Chunk::SingleOwner(chunk)).await.unwrap();
+        }
+
+        let (index, chunk) = feed.find_latest(&store).await.unwrap().unwrap();
+        assert_eq!(index, 5);
+        assert_eq!(chunk.data().as_ref(), 5u64.to_be_bytes());
+
+        // updates of another topic are not found
+        let other = SequentialFeed::new(signer.address(), B256::ZERO);
+        assert!(other.find_latest(&store).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_other_signer() {
+        let feed = SequentialFeed::new(Address::ZERO, B256::ZERO);
+        assert!(matches!(
+            feed.update(PrivateKeySigner::random(), 0, b"foo".to_vec())
+                .await,
+            Err(ChunkError::Verification { .. })
+        ));
+    }
+}
@@ -1,11 +1,11 @@
-use nectar_primitives_traits::BRANCHES;
-
+pub mod access;
 pub mod bmt;
 pub mod chunk;
 pub mod distance;
+pub mod feed;
+pub mod postage;
 pub mod proximity;
+pub mod store;
 
-const ENCRYPTED_BRANCHES: usize = BRANCHES / 2;
 pub(crate) const MAX_PO: usize = 31;
 pub(crate) const EXTENDED_PO: usize = MAX_PO + 5;
-const MAX_BINS: usize = MAX_PO + 1;
@@ -0,0 +1,32 @@
+use alloy::primitives::{Address, SignatureError};
+use thiserror::Error;
+
+mod stamp;
+#[cfg(test)]
+mod test_fixtures;
+
+pub use stamp::{digest, PostageStamp, STAMP_SIZE};
+
+#[derive(Error, Debug)]
+pub enum StampError {
+    #[error("Crypto error: {0}")]
+    Signature(#[from] SignatureError),
+
+    #[error("Invalid stamp length: {0}")]
+    InvalidLength(usize),
+
+    #[error("Bucket mismatch (expected: {expected}, got: {got})")]
+    BucketMismatch { expected: u32, got: u32 },
+
+    #[error("Bucket index {index} out of range (limit: {limit})")]
+    InvalidIndex { index: u32, limit: u32 },
+
+    #[error("Invalid bucket depth {bucket_depth} for depth {depth}")]
+    InvalidBucketDepth { depth: u8, bucket_depth: u8 },
+
+    #[error("Owner mismatch (expected: {expected}, recovered: {recovered})")]
+    OwnerMismatch {
+        expected: Address,
+        recovered: Address,
+    },
+}
@@ -0,0 +1,442 @@
+use alloy::primitives::{keccak256, Address, Keccak256, PrimitiveSignature, B256};
+use bytes::{Bytes, BytesMut};
+use nectar_primitives_traits::{
+    stamp::{STAMP_INDEX_SIZE, STAMP_TIMESTAMP_SIZE},
+    BatchId, Chunk, ChunkAddress, Stamp,
+};
+use std::sync::OnceLock;
+
+use super::StampError;
+
+const BATCH_ID_SIZE: usize = std::mem::size_of::<BatchId>();
+const SIGNATURE_SIZE: usize = 65;
+/// Size of a serialised stamp
+pub const STAMP_SIZE: usize =
+    BATCH_ID_SIZE + STAMP_INDEX_SIZE + STAMP_TIMESTAMP_SIZE + SIGNATURE_SIZE;
+
+/// A postage stamp, proving that a chunk has been paid for by a postage batch.
+#[derive(Debug, Clone)]
+pub struct PostageStamp {
+    batch_id: BatchId,
+    index: u64,
+    timestamp: u64,
+    signature: PrimitiveSignature,
+    cached_proof_data: OnceLock<Bytes>,
+}
+
+impl PostageStamp {
+    /// Create a new stamp from its parts. The index is the collision bucket in the upper 32 bits
+    /// and the position within the bucket in the lower 32 bits.
+    pub fn new(
+        batch_id: BatchId,
+        index: u64,
+        timestamp: u64,
+        signature: PrimitiveSignature,
+    ) -> Self {
+        Self {
+            batch_id,
+            index,
+            timestamp,
+            signature,
+            cached_proof_data: OnceLock::new(),
+        }
+    }
+
+    /// Returns the stamp serialised in Bee's layout:
+    /// `batch_id (32) || index (8) || timestamp (8) || signature (65)`, all big endian.
+    pub fn proof_data(&self) -> &Bytes {
+        self.cached_proof_data.get_or_init(|| {
+            let mut bytes = BytesMut::with_capacity(STAMP_SIZE);
+            bytes.extend_from_slice(self.batch_id.as_slice());
+            bytes.extend_from_slice(&self.index.to_be_bytes());
+            bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+            bytes.extend_from_slice(&self.signature.as_bytes());
+            bytes.freeze()
+        })
+    }
+
+    /// Returns the stamp in Bee's 113 byte wire format, see `proof_data`
+    pub fn to_bytes(&self) -> [u8; STAMP_SIZE] {
+        self.proof_data().as_ref().try_into().unwrap()
+    }
+
+    /// Parse a stamp from Bee's 113 byte wire format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, StampError> {
+        if bytes.len() != STAMP_SIZE {
+            return Err(StampError::InvalidLength(bytes.len()));
+        }
+
+        let (batch_id, rest) = bytes.split_at(BATCH_ID_SIZE);
+        let (index, rest) = rest.split_at(STAMP_INDEX_SIZE);
+        let (timestamp, signature) = rest.split_at(STAMP_TIMESTAMP_SIZE);
+
+        Ok(Self::new(
+            BatchId::from_slice(batch_id),
+            u64::from_be_bytes(index.try_into().unwrap()),
+            u64::from_be_bytes(timestamp.try_into().unwrap()),
+            PrimitiveSignature::try_from(signature)?,
+        ))
+    }
+}
+
+impl PostageStamp {
+    /// The collision bucket of the stamp, ie. the upper 32 bits of the index
+    pub fn bucket(&self) -> u32 {
+        (self.index >> 32) as u32
+    }
+
+    /// The position of the stamp within its bucket, ie. the lower 32 bits of the index
+    pub fn bucket_index(&self) -> u32 {
+        self.index as u32
+    }
+
+    /// Verify that the stamp's index is valid for the chunk at `address` in a batch of `depth`
+    /// and `bucket_depth`: the bucket must be the one the address falls into, and the position
+    /// within the bucket must not exceed the bucket's capacity of `2^(depth - bucket_depth)`.
+    /// Depths with no valid bucket capacity are rejected with [`StampError::InvalidBucketDepth`].
+    pub fn verify_index(
+        &self,
+        address: ChunkAddress,
+        depth: u8,
+        bucket_depth: u8,
+    ) -> Result<(), StampError> {
+        let limit = bucket_capacity(depth, bucket_depth)?;
+
+        let expected = bucket_of(address, bucket_depth);
+        if self.bucket() != expected {
+            return Err(StampError::BucketMismatch {
+                expected,
+                got: self.bucket(),
+            });
+        }
+
+        if self.bucket_index() >= limit {
+            return Err(StampError::InvalidIndex {
+                index: self.bucket_index(),
+                limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the digest signed by the batch owner:
+    /// `keccak256(chunk_address || batch_id || index || timestamp)`
+    pub fn digest(&self, address: ChunkAddress) -> B256 {
+        digest(address, self.batch_id, self.index, self.timestamp)
+    }
+
+    /// Recover the address that signed the stamp for the chunk at `address`
+    pub fn recover_signer(&self, address: ChunkAddress) -> Result<Address, StampError> {
+        Ok(self
+            .signature
+            .recover_address_from_msg(self.digest(address))?)
+    }
+
+    /// Verify that the stamp for the chunk at `address` was signed by the batch `owner`
+    pub fn verify_signature(
+        &self,
+        address: ChunkAddress,
+        owner: Address,
+    ) -> Result<(), StampError> {
+        let recovered = self.recover_signer(address)?;
+        if recovered != owner {
+            return Err(StampError::OwnerMismatch {
+                expected: owner,
+                recovered,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the stamp for `chunk` is valid for a batch of `owner`, `depth` and
+    /// `bucket_depth`: its index must fall in the chunk's collision bucket, see `verify_index`,
+    /// and it must be signed by the owner. Returns the recovered signer. This is the check a
+    /// storage node performs before accepting a chunk.
+    pub fn valid_for(
+        &self,
+        chunk: &impl Chunk,
+        owner: Address,
+        depth: u8,
+        bucket_depth: u8,
+    ) -> Result<Address, StampError> {
+        self.verify_index(chunk.address(), depth, bucket_depth)?;
+        self.verify_signature(chunk.address(), owner)?;
+
+        Ok(owner)
+    }
+}
+
+/// The digest of a stamp with the given parts for the chunk at `address`, which the batch owner
+/// signs as an EIP-191 personal message. Issuers that sign out of band, eg. with a remote signer,
+/// sign this digest and assemble the stamp with `PostageStamp::new`.
+pub fn digest(address: ChunkAddress, batch_id: BatchId, index: u64, timestamp: u64) -> B256 {
+    let mut hasher = Keccak256::new();
+    hasher.update(address);
+    hasher.update(batch_id);
+    hasher.update(index.to_be_bytes());
+    hasher.update(timestamp.to_be_bytes());
+    hasher.finalize()
+}
+
+/// The number of stamps each collision bucket of a batch holds, `2^(depth - bucket_depth)`.
+///
+/// Buckets are numbered by the leading `bucket_depth` bits of chunk addresses and positions
+/// within a bucket are 32 bits, so a bucket depth above 32 or above the depth, or a capacity of
+/// `2^32` or more, is rejected.
+pub(crate) fn bucket_capacity(depth: u8, bucket_depth: u8) -> Result<u32, StampError> {
+    depth
+        .checked_sub(bucket_depth)
+        .filter(|_| bucket_depth <= 32)
+        .and_then(|delta| 1u32.checked_shl(delta as u32))
+        .ok_or(StampError::InvalidBucketDepth {
+            depth,
+            bucket_depth,
+        })
+}
+
+/// The collision bucket that the chunk at `address` falls into, ie. the first `bucket_depth`
+/// bits of the address.
+pub(crate) fn bucket_of(address: ChunkAddress, bucket_depth: u8) -> u32 {
+    u32::from_be_bytes(address[..4].try_into().unwrap())
+        .checked_shr(32 - bucket_depth as u32)
+        .unwrap_or(0)
+}
+
+impl PartialEq for PostageStamp {
+    fn eq(&self, other: &Self) -> bool {
+        self.batch_id == other.batch_id
+            && self.index == other.index
+            && self.timestamp == other.timestamp
+            && self.signature == other.signature
+    }
+}
+
+impl Eq for PostageStamp {}
+
+impl Stamp for PostageStamp {
+    fn batch_id(&self) -> BatchId {
+        self.batch_id
+    }
+
+    fn index(&self) -> u64 {
+        self.index
+    }
+
+    fn sig(&self) -> PrimitiveSignature {
+        self.signature
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// The hash of the serialised stamp
+    fn hash(&self) -> B256 {
+        keccak256(self.proof_data())
+    }
+
+    fn to_bytes(&self) -> Bytes {
+        self.proof_data().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, Signer, SignerSync};
+
+    use crate::{
+        chunk::ContentChunk,
+        postage::test_fixtures::{BATCH_ID, CHUNK_ADDRESS, OWNER_PK, STAMP},
+    };
+
+    fn test_stamp() -> PostageStamp {
+        PostageStamp::new(
+            BATCH_ID,
+            52197 << 32,
+            1688492510651,
+            PrimitiveSignature::try_from(&STAMP[48..]).unwrap(),
+        )
+    }
+
+    #[test]
+    fn proof_data_layout() {
+        let stamp = test_stamp();
+        let proof_data = stamp.proof_data();
+
+        assert_eq!(proof_data.len(), STAMP_SIZE);
+        assert_eq!(proof_data.as_ref(), STAMP.as_slice());
+
+        // the serialisation is cached
+        assert!(std::ptr::eq(proof_data, stamp.proof_data()));
+        assert_eq!(stamp.hash(), keccak256(STAMP));
+    }
+
+    #[test]
+    fn stamp_trait_object() {
+        let stamp = test_stamp();
+        let stamp: &dyn Stamp = &stamp;
+
+        assert_eq!(stamp.batch_id(), BATCH_ID);
+        assert_eq!(stamp.index(), 52197 << 32);
+        assert_eq!(stamp.timestamp(), 1688492510651);
+        assert_eq!(stamp.sig().as_bytes(), STAMP[48..]);
+        assert_eq!(Stamp::to_bytes(stamp).as_ref(), STAMP.as_slice());
+        assert_eq!(stamp.hash(), keccak256(STAMP));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let stamp = PostageStamp::from_bytes(&STAMP).unwrap();
+        assert_eq!(stamp, test_stamp());
+        assert_eq!(stamp.bucket(), 52197);
+        assert_eq!(stamp.timestamp(), 1688492510651);
+        assert_eq!(stamp.to_bytes(), STAMP);
+
+        assert!(matches!(
+            PostageStamp::from_bytes(&STAMP[1..]),
+            Err(StampError::InvalidLength(112))
+        ));
+    }
+
+    #[test]
+    fn batch_id_conversions() {
+        let hex = "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369";
+        assert_eq!(hex.parse::<BatchId>().unwrap(), BATCH_ID);
+        assert_eq!(format!("0x{hex}").parse::<BatchId>().unwrap(), BATCH_ID);
+        assert!(hex[..62].parse::<BatchId>().is_err());
+
+        let bytes: [u8; 32] = BATCH_ID.into();
+        assert_eq!(BatchId::from(bytes), BATCH_ID);
+        assert_eq!(BatchId::try_from(&bytes[..]).unwrap(), BATCH_ID);
+        assert!(BatchId::try_from(&bytes[1..]).is_err());
+
+        let slice: &[u8] = BATCH_ID.as_ref();
+        assert_eq!(slice, bytes);
+
+        // and back from a stamp's wire format
+        let decoded = PostageStamp::from_bytes(&STAMP).unwrap();
+        assert_eq!(decoded.batch_id(), BATCH_ID);
+    }
+
+    #[test]
+    fn verify_signature() {
+        let owner = PrivateKeySigner::from_bytes(&OWNER_PK).unwrap().address();
+        let stamp = test_stamp();
+
+        assert_eq!(stamp.recover_signer(CHUNK_ADDRESS).unwrap(), owner);
+        assert!(stamp.verify_signature(CHUNK_ADDRESS, owner).is_ok());
+
+        // a tampered timestamp no longer recovers to the owner
+        let tampered = PostageStamp::new(
+            stamp.batch_id(),
+            stamp.index(),
+            stamp.timestamp() + 1,
+            stamp.sig(),
+        );
+        assert!(matches!(
+            tampered.verify_signature(CHUNK_ADDRESS, owner),
+            Err(StampError::OwnerMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn verify_fresh_signature() {
+        let signer = PrivateKeySigner::random();
+        let unsigned = PostageStamp::new(BatchId::repeat_byte(1), 7, 42, test_stamp().sig());
+        let signature = signer
+            .sign_message(unsigned.digest(CHUNK_ADDRESS).as_slice())
+            .await
+            .unwrap();
+
+        let stamp = PostageStamp::new(BatchId::repeat_byte(1), 7, 42, signature);
+        assert!(stamp
+            .verify_signature(CHUNK_ADDRESS, signer.address())
+            .is_ok());
+        assert!(stamp
+            .verify_signature(CHUNK_ADDRESS, Address::ZERO)
+            .is_err());
+    }
+
+    #[test]
+    fn valid_for() {
+        let chunk = ContentChunk::new(&b"hello wordl"[..]).unwrap();
+        assert_eq!(chunk.address(), CHUNK_ADDRESS);
+
+        let owner = PrivateKeySigner::from_bytes(&OWNER_PK).unwrap().address();
+        assert_eq!(
+            test_stamp().valid_for(&chunk, owner, 18, 16).unwrap(),
+            owner
+        );
+
+        // the same stamp signed by another key is rejected
+        let impostor = PrivateKeySigner::random();
+        let signature = impostor
+            .sign_message_sync(test_stamp().digest(CHUNK_ADDRESS).as_slice())
+            .unwrap();
+        let forged = PostageStamp::new(
+            test_stamp().batch_id(),
+            test_stamp().index(),
+            test_stamp().timestamp(),
+            signature,
+        );
+        assert!(matches!(
+            forged.valid_for(&chunk, owner, 18, 16),
+            Err(StampError::OwnerMismatch { recovered, .. }) if recovered == impostor.address()
+        ));
+
+        // a stamp signed by the owner, but claiming another collision bucket
+        let index = (52198 << 32) | test_stamp().bucket_index() as u64;
+        let signature = PrivateKeySigner::from_bytes(&OWNER_PK)
+            .unwrap()
+            .sign_message_sync(digest(CHUNK_ADDRESS, BATCH_ID, index, 0).as_slice())
+            .unwrap();
+        let cross_bucket = PostageStamp::new(BATCH_ID, index, 0, signature);
+        assert!(cross_bucket.verify_signature(CHUNK_ADDRESS, owner).is_ok());
+        assert!(matches!(
+            cross_bucket.valid_for(&chunk, owner, 18, 16),
+            Err(StampError::BucketMismatch {
+                expected: 52197,
+                got: 52198
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_index() {
+        let stamp = test_stamp();
+        assert_eq!(stamp.bucket(), 52197);
+        assert_eq!(stamp.bucket_index(), 0);
+
+        // the chunk address starts with 0xcbe5, the stamp's bucket at bucket depth 16
+        assert!(stamp.verify_index(CHUNK_ADDRESS, 18, 16).is_ok());
+
+        // the same index forged into a neighbouring bucket
+        let forged = PostageStamp::new(stamp.batch_id(), 52198 << 32, 0, stamp.sig());
+        assert!(matches!(
+            forged.verify_index(CHUNK_ADDRESS, 18, 16),
+            Err(StampError::BucketMismatch {
+                expected: 52197,
+                got: 52198
+            })
+        ));
+
+        // the bucket only holds 2^(18 - 16) stamps
+        let overflow = PostageStamp::new(stamp.batch_id(), (52197 << 32) | 4, 0, stamp.sig());
+        assert!(matches!(
+            overflow.verify_index(CHUNK_ADDRESS, 18, 16),
+            Err(StampError::InvalidIndex { index: 4, limit: 4 })
+        ));
+
+        // depths without a valid bucket capacity are rejected rather than panicking
+        for (depth, bucket_depth) in [(16, 18), (48, 16), (40, 33)] {
+            assert!(matches!(
+                stamp.verify_index(CHUNK_ADDRESS, depth, bucket_depth),
+                Err(StampError::InvalidBucketDepth { depth: d, bucket_depth: b })
+                    if d == depth && b == bucket_depth
+            ));
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use alloy::{
+    hex,
+    primitives::{b256, B256},
+};
+use nectar_primitives_traits::{BatchId, ChunkAddress};
+
+use super::STAMP_SIZE;
+
+// stamp produced by bee for the chunk containing "hello wordl"
+pub(super) const STAMP: [u8; STAMP_SIZE] = hex!(
+    "c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369\
+    0000cbe500000000\
+    0000018921ff0dbb\
+    29169df9e6364e26c6ca6b17745c10b9d6a36ea38e204f2e3cc64a8373c0661f\
+    5bb0a347c61d8d1689b0dcf8354117686a6a18d08cff927f526de5fc61b2b7491b"
+);
+
+// batch the stamp was issued from
+pub(super) const BATCH_ID: BatchId =
+    b256!("c3387832bb1b88acbcd0ffdb65a08ef077d98c08d4bee576a72dbe3d36761369");
+
+// address of the chunk containing "hello wordl"
+pub(super) const CHUNK_ADDRESS: ChunkAddress =
+    b256!("cbe563e4865fd01948a1180081bbb7e144204344012dea8ce6e86d36dbc63495");
+
+// private key of the owner of the batch that signed the stamp
+pub(super) const OWNER_PK: B256 =
+    b256!("be52c649a4c560a1012daa572d4e81627bcce20ca14e007aef87808a7fadd3d0");
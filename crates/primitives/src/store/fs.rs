@@ -0,0 +1,162 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use alloy::hex;
+use bytes::Bytes;
+use nectar_primitives_traits::{
+    chunk::{ChunkError, Result},
+    Chunk as _, ChunkAddress, ChunkStore,
+};
+
+use crate::chunk::{Chunk, ContentChunk, SingleOwnerChunk};
+
+// numbers the temporary files written by this process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A chunk store keeping one file per chunk, at `<dir>/<first byte as hex>/<address>.chunk`.
+///
+/// Chunks are verified against their address when read back, so a corrupted or misplaced file
+/// is reported as an error rather than returned.
+#[derive(Debug, Clone)]
+pub struct FsChunkStore {
+    dir: PathBuf,
+}
+
+impl FsChunkStore {
+    /// Create a store rooted at `dir`. Directories are created as chunks are written.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Write a chunk, returning its address
+    pub fn put(&self, chunk: Chunk) -> Result<ChunkAddress> {
+        let address = chunk.address();
+        let path = self.path(&address);
+
+        fs::create_dir_all(path.parent().expect("chunk path has a parent"))?;
+
+        // write to a temporary file first so that a partial write never looks like a chunk, named
+        // uniquely so that concurrent writes of the same chunk don't rename each other's file
+        let tmp = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, Bytes::from(chunk))?;
+        fs::rename(tmp, path)?;
+
+        Ok(address)
+    }
+
+    /// Read and verify the chunk at `address`, returning `None` if it isn't stored
+    pub fn get(&self, address: &ChunkAddress) -> Result<Option<Chunk>> {
+        let bytes = match fs::read(self.path(address)) {
+            Ok(bytes) => Bytes::from(bytes),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // the stored bytes don't record the chunk type, so accept whichever type verifies
+        if let Ok(chunk) = ContentChunk::try_from(bytes.clone()) {
+            if chunk.address() == *address {
+                return Ok(Some(Chunk::Content(chunk)));
+            }
+        }
+
+        if let Ok(chunk) = SingleOwnerChunk::try_from(bytes) {
+            if chunk.verify(*address).is_ok() {
+                return Ok(Some(Chunk::SingleOwner(chunk)));
+            }
+        }
+
+        Err(ChunkError::format(
+            "stored chunk does not match its address",
+        ))
+    }
+
+    /// Whether a chunk is stored at `address`
+    pub fn has(&self, address: &ChunkAddress) -> bool {
+        self.path(address).is_file()
+    }
+
+    fn path(&self, address: &ChunkAddress) -> PathBuf {
+        let address = hex::encode(address);
+        self.dir
+            .join(&address[..2])
+            .join(format!("{}.chunk", address))
+    }
+}
+
+impl ChunkStore for FsChunkStore {
+    type Chunk = Chunk;
+
+    async fn get(&self, address: ChunkAddress) -> Result<Option<Chunk>> {
+        FsChunkStore::get(self, &address)
+    }
+
+    async fn put(&self, chunk: Chunk) -> Result<ChunkAddress> {
+        FsChunkStore::put(self, chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{primitives::B256, signers::local::PrivateKeySigner};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_dir() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("fs-chunk-store-{}-{}", std::process::id(), nanos))
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        let dir = temp_dir();
+        let store = FsChunkStore::new(&dir);
+
+        let content = ContentChunk::new(b"hello world".to_vec()).unwrap();
+        let soc = SingleOwnerChunk::new(B256::ZERO, b"foo".to_vec(), PrivateKeySigner::random())
+            .await
+            .unwrap();
+
+        for chunk in [Chunk::Content(content), Chunk::SingleOwner(soc)] {
+            let address = chunk.address();
+            let hex = hex::encode(address);
+            assert!(!store.has(&address));
+            assert!(store.get(&address).unwrap().is_none());
+
+            assert_eq!(store.put(chunk.clone()).unwrap(), address);
+
+            assert!(store.has(&address));
+            assert!(dir.join(&hex[..2]).join(format!("{}.chunk", hex)).is_file());
+            assert_eq!(store.get(&address).unwrap(), Some(chunk));
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_corrupted_chunk() {
+        let dir = temp_dir();
+        let store = FsChunkStore::new(&dir);
+
+        let chunk = ContentChunk::new(b"hello world".to_vec()).unwrap();
+        let address = store.put(Chunk::Content(chunk)).unwrap();
+
+        let path = store.path(&address);
+        let mut bytes = fs::read(&path).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(&path, bytes).unwrap();
+
+        assert!(matches!(store.get(&address), Err(ChunkError::Format(_))));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use nectar_primitives_traits::{chunk::Result, ChunkAddress, ChunkStore};
+
+use crate::chunk::Chunk;
+
+/// A chunk store keeping chunks in memory, eg. for tests or short lived caches.
+#[derive(Debug, Default)]
+pub struct MemoryChunkStore {
+    chunks: RwLock<HashMap<ChunkAddress, Chunk>>,
+}
+
+impl MemoryChunkStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of chunks stored
+    pub fn len(&self) -> usize {
+        self.chunks.read().unwrap().len()
+    }
+
+    /// Whether the store holds no chunks
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ChunkStore for MemoryChunkStore {
+    type Chunk = Chunk;
+
+    async fn get(&self, address: ChunkAddress) -> Result<Option<Chunk>> {
+        Ok(self.chunks.read().unwrap().get(&address).cloned())
+    }
+
+    async fn put(&self, chunk: Chunk) -> Result<ChunkAddress> {
+        let address = chunk.address();
+        self.chunks.write().unwrap().insert(address, chunk);
+        Ok(address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::{ContentChunk, SingleOwnerChunk};
+    use alloy::{primitives::B256, signers::local::PrivateKeySigner};
+
+    #[tokio::test]
+    async fn put_then_get() {
+        let store = MemoryChunkStore::new();
+
+        let content = ContentChunk::new(b"hello world".to_vec()).unwrap();
+        let soc = SingleOwnerChunk::new(B256::ZERO, b"foo".to_vec(), PrivateKeySigner::random())
+            .await
+            .unwrap();
+
+        for chunk in [Chunk::Content(content), Chunk::SingleOwner(soc)] {
+            let address = store.put(chunk.clone()).await.unwrap();
+            assert_eq!(address, chunk.address());
+            assert_eq!(store.get(address).await.unwrap(), Some(chunk));
+        }
+        assert_eq!(store.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn missing_address() {
+        let store = MemoryChunkStore::new();
+        assert!(store.is_empty());
+        assert_eq!(store.get(B256::repeat_byte(1)).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,7 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod fs;
+mod memory;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use fs::FsChunkStore;
+pub use memory::MemoryChunkStore;